@@ -0,0 +1,102 @@
+//! Compare the sweep aggregator against a naive filter per period, on a synthetic dataset of a
+//! million nodes.
+//!
+//! Run with `cargo bench --bench aggregate`.
+
+use std::{
+    collections::HashSet,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use chrono::{TimeZone, Utc};
+use node_counter::{aggregate, Granularity, Node, Period, Resources, DEFAULT_STALENESS};
+
+const NODE_COUNT: u32 = 1_000_000;
+const FARM_COUNT: u64 = 5_000;
+const ITERATIONS: u32 = 5;
+
+fn main() {
+    let from = Utc.with_ymd_and_hms(2018, 1, 1, 0, 0, 0).unwrap();
+    let to = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+    let nodes = synthetic_nodes(from.timestamp(), to.timestamp());
+
+    for granularity in [
+        Granularity::Monthly,
+        Granularity::Weekly,
+        Granularity::Daily,
+    ] {
+        let periods = granularity.periods(from, to);
+        let sweep = time(|| aggregate(&nodes, &periods, Some(DEFAULT_STALENESS)).len());
+        println!(
+            "{:>8} ({:>4} periods): sweep {sweep:>10.2?}",
+            granularity.name(),
+            periods.len()
+        );
+    }
+
+    // The naive approach scales with periods * nodes, so only run it on monthly periods.
+    let periods = Granularity::Monthly.periods(from, to);
+    let sweep = time(|| aggregate(&nodes, &periods, Some(DEFAULT_STALENESS)).len());
+    let naive = time(|| naive_aggregate(&nodes, &periods));
+    println!(
+        " monthly ({:>4} periods): naive {naive:>10.2?}, speedup {:.1}x",
+        periods.len(),
+        naive.as_secs_f64() / sweep.as_secs_f64()
+    );
+}
+
+/// Run `f` a couple of times and return the average duration.
+fn time(mut f: impl FnMut() -> usize) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    start.elapsed() / ITERATIONS
+}
+
+/// The original aggregation: filter all nodes for every period.
+fn naive_aggregate(nodes: &[Node], periods: &[Period]) -> usize {
+    periods
+        .iter()
+        .map(|period| {
+            let ts = period.start.timestamp();
+            let (node_count, farms, resources) =
+                nodes.iter().filter(|node| node.created() < ts).fold(
+                    (0, HashSet::new(), Resources::default()),
+                    |(node_count, mut farms, mut resources), node| {
+                        farms.insert(node.farm_id());
+                        resources += node.resources_total();
+                        (node_count + 1, farms, resources)
+                    },
+                );
+            black_box((node_count, farms.len(), resources));
+        })
+        .count()
+}
+
+/// Generate nodes with random creation times in the given range, in random order.
+fn synthetic_nodes(from: i64, to: i64) -> Vec<Node> {
+    // xorshift, good enough for synthetic data and avoids a dependency.
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    (0..NODE_COUNT)
+        .map(|node_id| {
+            let created = from + (next() % (to - from) as u64) as i64;
+            let farm_id = (next() % FARM_COUNT) as u32 + 1;
+            let resources = Resources::new(
+                next() % 64 + 1,
+                (next() % 256 + 1) << 30,
+                (next() % 4096 + 1) << 30,
+                (next() % 16384) << 30,
+            );
+            Node::new(node_id + 1, farm_id, created, resources)
+        })
+        .collect()
+}
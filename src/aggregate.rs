@@ -0,0 +1,920 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    hash::Hash,
+    time::Duration,
+};
+
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, NaiveTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    types::{Node, Resources},
+    Continent, Country, NodeCertification, NodeStatus,
+};
+
+/// The size of the periods nodes are aggregated in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    /// One period per day.
+    Daily,
+    /// One period per ISO week, starting on monday.
+    Weekly,
+    /// One period per calendar month.
+    #[default]
+    Monthly,
+    /// One period per calendar quarter.
+    Quarterly,
+    /// One period per calendar year.
+    Yearly,
+}
+
+impl Granularity {
+    /// All granularities.
+    pub const ALL: [Granularity; 5] = [
+        Granularity::Daily,
+        Granularity::Weekly,
+        Granularity::Monthly,
+        Granularity::Quarterly,
+        Granularity::Yearly,
+    ];
+
+    /// Short lowercase name of the granularity.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Granularity::Daily => "daily",
+            Granularity::Weekly => "weekly",
+            Granularity::Monthly => "monthly",
+            Granularity::Quarterly => "quarterly",
+            Granularity::Yearly => "yearly",
+        }
+    }
+
+    /// Get the start of the period following the one starting at `start`.
+    fn next(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Granularity::Daily => start + Days::new(1),
+            Granularity::Weekly => start + Days::new(7),
+            Granularity::Monthly => start + Months::new(1),
+            Granularity::Quarterly => start + Months::new(3),
+            Granularity::Yearly => start + Months::new(12),
+        }
+    }
+
+    /// Align a timestamp to the start of the period it falls in.
+    fn align(&self, ts: DateTime<Utc>) -> DateTime<Utc> {
+        let date = ts.date_naive();
+        let date = match self {
+            Granularity::Daily => date,
+            Granularity::Weekly => date - Days::new(date.weekday().num_days_from_monday() as u64),
+            Granularity::Monthly => date.with_day(1).unwrap(),
+            Granularity::Quarterly => {
+                NaiveDate::from_ymd_opt(date.year(), (date.month0() / 3) * 3 + 1, 1).unwrap()
+            }
+            Granularity::Yearly => NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap(),
+        };
+        date.and_time(NaiveTime::MIN).and_utc()
+    }
+
+    /// All periods in the given range. The first period is the one `from` falls in, the last
+    /// one is the last period starting before or at `to`.
+    pub fn periods(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Period> {
+        let mut periods = Vec::new();
+        let mut start = self.align(from);
+        while start <= to {
+            let end = self.next(start);
+            periods.push(Period { start, end });
+            start = end;
+        }
+        periods
+    }
+}
+
+name_impls!(Granularity, "granularity");
+
+/// A time period, from `start` (inclusive) until `end` (exclusive).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Period {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// Aggregated totals of all nodes created before the start of a period, and of the nodes created
+/// during the period.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct PeriodAggregate {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    node_count: u64,
+    farms: u64,
+    resources: Resources,
+    used_resources: Resources,
+    reporting_usage: NodeTotals,
+    new_nodes: u64,
+    new_farms: u64,
+    new_resources: Resources,
+    mom_growth: Option<f64>,
+    yoy_growth: Option<f64>,
+    certification: [NodeTotals; 2],
+    activity: [NodeTotals; 3],
+}
+
+impl PeriodAggregate {
+    /// Start of the period.
+    pub fn start(&self) -> DateTime<Utc> {
+        self.start
+    }
+
+    /// End of the period, this is the start of the next period.
+    pub fn end(&self) -> DateTime<Utc> {
+        self.end
+    }
+
+    /// Amount of nodes created before the start of the period.
+    pub fn node_count(&self) -> u64 {
+        self.node_count
+    }
+
+    /// Amount of distinct farms which have at least 1 node.
+    pub fn farms(&self) -> u64 {
+        self.farms
+    }
+
+    /// Total resources of all counted nodes.
+    pub fn resources(&self) -> &Resources {
+        &self.resources
+    }
+
+    /// Resources of the counted nodes in use at the time they were fetched. Only nodes which
+    /// reported their usage are included, see [`reporting_usage`](Self::reporting_usage).
+    pub fn used_resources(&self) -> &Resources {
+        &self.used_resources
+    }
+
+    /// Totals of the counted nodes which reported the resources they use.
+    pub fn reporting_usage(&self) -> &NodeTotals {
+        &self.reporting_usage
+    }
+
+    /// Amount of nodes created during the period.
+    pub fn new_nodes(&self) -> u64 {
+        self.new_nodes
+    }
+
+    /// Amount of farms which got their first node during the period.
+    pub fn new_farms(&self) -> u64 {
+        self.new_farms
+    }
+
+    /// Total resources of the nodes created during the period.
+    pub fn new_resources(&self) -> &Resources {
+        &self.new_resources
+    }
+
+    /// Percentage growth of the node count compared to 1 month before the start of the period.
+    /// `None` if there were no nodes at that time.
+    pub fn mom_growth(&self) -> Option<f64> {
+        self.mom_growth
+    }
+
+    /// Percentage growth of the node count compared to 1 year before the start of the period.
+    /// `None` if there were no nodes at that time.
+    pub fn yoy_growth(&self) -> Option<f64> {
+        self.yoy_growth
+    }
+
+    /// Totals of the counted nodes with the given certification. Nodes with an unknown
+    /// certification are not included in any certification.
+    pub fn by_certification(&self, certification: NodeCertification) -> &NodeTotals {
+        &self.certification[certification as usize]
+    }
+
+    /// Totals of the counted nodes with the given status at the start of the period, see
+    /// [`Node::status_at`]. Nodes with unknown activity are not included in any status.
+    pub fn by_status(&self, status: NodeStatus) -> &NodeTotals {
+        &self.activity[status as usize]
+    }
+}
+
+/// Totals of a subset of the nodes created before the start of a period, like the nodes with a
+/// single certification or status.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct NodeTotals {
+    node_count: u64,
+    resources: Resources,
+}
+
+impl NodeTotals {
+    /// Amount of nodes in the subset.
+    pub fn node_count(&self) -> u64 {
+        self.node_count
+    }
+
+    /// Total resources of the nodes in the subset.
+    pub fn resources(&self) -> &Resources {
+        &self.resources
+    }
+
+    fn add(&mut self, resources: &Resources) {
+        self.node_count += 1;
+        self.resources += resources;
+    }
+
+    fn remove(&mut self, resources: &Resources) {
+        self.node_count -= 1;
+        self.resources = self.resources - *resources;
+    }
+}
+
+/// Running totals of all nodes created before a point in time.
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    node_count: u64,
+    farms: u64,
+    resources: Resources,
+    used_resources: Resources,
+    reporting_usage: NodeTotals,
+}
+
+/// Aggregate the given nodes for every period. With a `staleness`, the nodes are also split by
+/// status, and nodes which didn't report for `staleness` are counted as down, see
+/// [`Node::status_at`]. Without one, the totals per status are zero.
+///
+/// Nodes are sorted by creation time once, after which all period boundaries are swept in order
+/// while keeping running totals.
+pub fn aggregate(
+    nodes: &[Node],
+    periods: &[Period],
+    staleness: Option<Duration>,
+) -> Vec<PeriodAggregate> {
+    let mut sorted = nodes.iter().collect::<Vec<_>>();
+    sorted.sort_unstable_by_key(|node| node.created());
+    let created = sorted.iter().map(|node| node.created()).collect::<Vec<_>>();
+    let count_before =
+        |ts: DateTime<Utc>| created.partition_point(|&created| created < ts.timestamp()) as u64;
+
+    let mut boundaries = periods
+        .iter()
+        .flat_map(|period| [period.start.timestamp(), period.end.timestamp()])
+        .collect::<Vec<_>>();
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    let mut totals = Vec::with_capacity(boundaries.len());
+    let mut running = Totals::default();
+    let mut certification = [NodeTotals::default(); 2];
+    let mut farms = HashSet::new();
+    let mut nodes = sorted.iter().peekable();
+    for &boundary in &boundaries {
+        while let Some(node) = nodes.next_if(|node| node.created() < boundary) {
+            if farms.insert(node.farm_id()) {
+                running.farms += 1;
+            }
+            running.node_count += 1;
+            running.resources += node.resources_total();
+            if let Some(used) = node.resources_used() {
+                running.used_resources += used;
+                running.reporting_usage.add(node.resources_total());
+            }
+            if let Some(class) = node.certification() {
+                certification[class as usize].add(node.resources_total());
+            }
+        }
+        totals.push((running, certification));
+    }
+    let activity = match staleness {
+        Some(staleness) => activity(&sorted, &boundaries, staleness),
+        None => vec![Default::default(); boundaries.len()],
+    };
+    let totals_at = |ts: DateTime<Utc>| {
+        // All period boundaries are present, so the search can't fail.
+        let idx = boundaries.binary_search(&ts.timestamp()).unwrap();
+        (totals[idx].0, totals[idx].1, activity[idx])
+    };
+
+    periods
+        .iter()
+        .map(|period| {
+            let (start, certification, activity) = totals_at(period.start);
+            let (end, _, _) = totals_at(period.end);
+
+            PeriodAggregate {
+                start: period.start,
+                end: period.end,
+                node_count: start.node_count,
+                farms: start.farms,
+                resources: start.resources,
+                used_resources: start.used_resources,
+                reporting_usage: start.reporting_usage,
+                new_nodes: end.node_count - start.node_count,
+                new_farms: end.farms - start.farms,
+                new_resources: end.resources - start.resources,
+                mom_growth: growth(
+                    count_before(period.start - Months::new(1)),
+                    start.node_count,
+                ),
+                yoy_growth: growth(
+                    count_before(period.start - Months::new(12)),
+                    start.node_count,
+                ),
+                certification,
+                activity,
+            }
+        })
+        .collect()
+}
+
+/// Totals per status of the nodes at every timestamp in `at`, which must be sorted.
+///
+/// The status of a node only changes at its creation, its last report, and when its last report
+/// becomes stale. Those changes are sorted once, after which the timestamps are swept in order.
+fn activity(nodes: &[&Node], at: &[i64], staleness: Duration) -> Vec<[NodeTotals; 3]> {
+    let staleness = i64::try_from(staleness.as_secs()).unwrap_or(i64::MAX);
+
+    // (time, node, status before, status after): the status after applies after `time`.
+    let mut changes = Vec::new();
+    for node in nodes {
+        let stale = node.updated_at().map(|t| t.saturating_add(staleness));
+        let mut times = [Some(node.created()), node.updated_at(), stale]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        times.sort_unstable();
+        times.dedup();
+
+        let mut previous = None;
+        for time in times {
+            let status = node.status_at(time.saturating_add(1), staleness);
+            if status != previous {
+                changes.push((time, node, previous, status));
+                previous = status;
+            }
+        }
+    }
+    changes.sort_unstable_by_key(|&(time, ..)| time);
+
+    let mut totals = Vec::with_capacity(at.len());
+    let mut running = [NodeTotals::default(); 3];
+    let mut changes = changes.into_iter().peekable();
+    for &ts in at {
+        while let Some((_, node, before, after)) = changes.next_if(|&(time, ..)| time < ts) {
+            if let Some(before) = before {
+                running[before as usize].remove(node.resources_total());
+            }
+            if let Some(after) = after {
+                running[after as usize].add(node.resources_total());
+            }
+        }
+        totals.push(running);
+    }
+    totals
+}
+
+/// Totals of the nodes of a single farm created before the start of a period.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct FarmAggregate {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    farm_id: u32,
+    node_count: u64,
+    resources: Resources,
+    used_resources: Resources,
+    reporting_usage: NodeTotals,
+}
+
+impl FarmAggregate {
+    /// Start of the period.
+    pub fn start(&self) -> DateTime<Utc> {
+        self.start
+    }
+
+    /// End of the period, this is the start of the next period.
+    pub fn end(&self) -> DateTime<Utc> {
+        self.end
+    }
+
+    /// Id of the farm.
+    pub fn farm_id(&self) -> u32 {
+        self.farm_id
+    }
+
+    /// Amount of nodes of the farm created before the start of the period.
+    pub fn node_count(&self) -> u64 {
+        self.node_count
+    }
+
+    /// Total resources of the counted nodes.
+    pub fn resources(&self) -> &Resources {
+        &self.resources
+    }
+
+    /// Resources of the counted nodes in use at the time they were fetched. Only nodes which
+    /// reported their usage are included, see [`reporting_usage`](Self::reporting_usage).
+    pub fn used_resources(&self) -> &Resources {
+        &self.used_resources
+    }
+
+    /// Totals of the counted nodes which reported the resources they use.
+    pub fn reporting_usage(&self) -> &NodeTotals {
+        &self.reporting_usage
+    }
+}
+
+/// Aggregate the given nodes per farm for every period. Per period, only farms with at least 1
+/// node are included, ordered by farm id.
+pub fn aggregate_by_farm(nodes: &[Node], periods: &[Period]) -> Vec<FarmAggregate> {
+    aggregate_grouped(nodes, periods, Node::farm_id)
+        .into_iter()
+        .map(|(period, farm_id, totals)| FarmAggregate {
+            start: period.start,
+            end: period.end,
+            farm_id,
+            node_count: totals.node_count,
+            resources: totals.resources,
+            used_resources: totals.used_resources,
+            reporting_usage: totals.reporting_usage,
+        })
+        .collect()
+}
+
+/// Aggregate the given nodes per group for every period, returning the totals of every group with
+/// at least 1 node at the start of the period, ordered by group.
+///
+/// Like [`aggregate`], the period starts are swept in order while keeping running totals per
+/// group.
+fn aggregate_grouped<K: Copy + Ord + Hash>(
+    nodes: &[Node],
+    periods: &[Period],
+    group: impl Fn(&Node) -> K,
+) -> Vec<(Period, K, Totals)> {
+    let mut sorted = nodes.iter().collect::<Vec<_>>();
+    sorted.sort_unstable_by_key(|node| node.created());
+
+    let mut starts = periods
+        .iter()
+        .map(|period| period.start.timestamp())
+        .collect::<Vec<_>>();
+    starts.sort_unstable();
+    starts.dedup();
+
+    let mut groups = Vec::with_capacity(starts.len());
+    let mut running = BTreeMap::<K, Totals>::new();
+    let mut farms = HashSet::new();
+    let mut nodes = sorted.iter().peekable();
+    for &start in &starts {
+        while let Some(node) = nodes.next_if(|node| node.created() < start) {
+            let key = group(node);
+            let totals = running.entry(key).or_default();
+            if farms.insert((key, node.farm_id())) {
+                totals.farms += 1;
+            }
+            totals.node_count += 1;
+            totals.resources += node.resources_total();
+            if let Some(used) = node.resources_used() {
+                totals.used_resources += used;
+                totals.reporting_usage.add(node.resources_total());
+            }
+        }
+        groups.push(running.clone());
+    }
+
+    periods
+        .iter()
+        .flat_map(|period| {
+            // All period starts are present, so the search can't fail.
+            let idx = starts.binary_search(&period.start.timestamp()).unwrap();
+            groups[idx]
+                .iter()
+                .map(move |(&key, &totals)| (*period, key, totals))
+        })
+        .collect()
+}
+
+/// The level at which nodes are grouped geographically.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    /// Group by ISO 3166-1 country.
+    #[default]
+    Country,
+    /// Group by the continent of the country.
+    Continent,
+}
+
+impl Region {
+    /// All region levels.
+    pub const ALL: [Region; 2] = [Region::Country, Region::Continent];
+
+    /// Short lowercase name of the region level.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Region::Country => "country",
+            Region::Continent => "continent",
+        }
+    }
+}
+
+name_impls!(Region, "region");
+
+/// Totals of the nodes in a single country or continent created before the start of a period.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct RegionAggregate {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    continent: Option<Continent>,
+    country: Option<&'static Country>,
+    node_count: u64,
+    farms: u64,
+    resources: Resources,
+}
+
+impl RegionAggregate {
+    /// Start of the period.
+    pub fn start(&self) -> DateTime<Utc> {
+        self.start
+    }
+
+    /// End of the period, this is the start of the next period.
+    pub fn end(&self) -> DateTime<Utc> {
+        self.end
+    }
+
+    /// The continent, `None` for nodes in an unknown country.
+    pub fn continent(&self) -> Option<Continent> {
+        self.continent
+    }
+
+    /// The country, only set when grouping by country. `None` for nodes in an unknown country.
+    pub fn country(&self) -> Option<&'static Country> {
+        self.country
+    }
+
+    /// Amount of nodes in the region created before the start of the period.
+    pub fn node_count(&self) -> u64 {
+        self.node_count
+    }
+
+    /// Amount of distinct farms with at least 1 counted node in the region.
+    pub fn farms(&self) -> u64 {
+        self.farms
+    }
+
+    /// Total resources of the counted nodes.
+    pub fn resources(&self) -> &Resources {
+        &self.resources
+    }
+}
+
+/// Aggregate the given nodes per country or continent for every period. Countries are
+/// normalised to ISO 3166-1, nodes in an unknown or unrecognised country are grouped together.
+/// Per period, only regions with at least 1 node are included, with the unknown region first.
+pub fn aggregate_by_region(
+    nodes: &[Node],
+    periods: &[Period],
+    region: Region,
+) -> Vec<RegionAggregate> {
+    let aggregate = |period: Period, continent, country, totals: Totals| RegionAggregate {
+        start: period.start,
+        end: period.end,
+        continent,
+        country,
+        node_count: totals.node_count,
+        farms: totals.farms,
+        resources: totals.resources,
+    };
+    match region {
+        Region::Country => {
+            aggregate_grouped(nodes, periods, |node| node.iso_country().map(Country::code))
+                .into_iter()
+                .map(|(period, code, totals)| {
+                    let country = code.and_then(Country::find);
+                    aggregate(period, country.map(Country::continent), country, totals)
+                })
+                .collect()
+        }
+        Region::Continent => aggregate_grouped(nodes, periods, |node| {
+            node.iso_country().map(Country::continent)
+        })
+        .into_iter()
+        .map(|(period, continent, totals)| aggregate(period, continent, None, totals))
+        .collect(),
+    }
+}
+
+/// The ids of the `n` farms with the most nodes in the last period of `rows`, largest first.
+/// Ties are broken by total CRU, then by the lowest farm id.
+pub fn largest_farms(rows: &[FarmAggregate], n: usize) -> Vec<u32> {
+    let Some(last) = rows.iter().map(FarmAggregate::start).max() else {
+        return Vec::new();
+    };
+    let mut farms = rows
+        .iter()
+        .filter(|row| row.start == last)
+        .collect::<Vec<_>>();
+    farms.sort_by(|a, b| {
+        b.node_count
+            .cmp(&a.node_count)
+            .then(b.resources.cru().cmp(&a.resources.cru()))
+            .then(a.farm_id.cmp(&b.farm_id))
+    });
+    farms.into_iter().take(n).map(|row| row.farm_id).collect()
+}
+
+/// Percentage growth from `old` to `new`, or `None` if `old` is 0.
+fn growth(old: u64, new: u64) -> Option<f64> {
+    if old == 0 {
+        return None;
+    }
+    Some((new as f64 - old as f64) / old as f64 * 100.)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::DEFAULT_STALENESS;
+
+    fn at(y: i32, m: u32, d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap()
+    }
+
+    fn starts(granularity: Granularity, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<NaiveDate> {
+        granularity
+            .periods(from, to)
+            .iter()
+            .map(|p| p.start.date_naive())
+            .collect()
+    }
+
+    #[test]
+    fn weekly_aligns_to_iso_week_across_year_boundary() {
+        // 2021-01-01 is a friday in ISO week 53 of 2020.
+        let friday = Utc.with_ymd_and_hms(2021, 1, 1, 13, 30, 0).unwrap();
+        assert_eq!(Granularity::Weekly.align(friday), at(2020, 12, 28));
+        // 2024-12-31 is a tuesday in ISO week 1 of 2025.
+        assert_eq!(
+            Granularity::Weekly.align(at(2024, 12, 31)),
+            at(2024, 12, 30)
+        );
+        assert_eq!(Granularity::Weekly.align(at(2025, 1, 5)), at(2024, 12, 30));
+        assert_eq!(Granularity::Weekly.next(at(2020, 12, 28)), at(2021, 1, 4));
+        assert_eq!(
+            starts(Granularity::Weekly, at(2020, 12, 30), at(2021, 1, 11)),
+            [at(2020, 12, 28), at(2021, 1, 4), at(2021, 1, 11)].map(|d| d.date_naive())
+        );
+    }
+
+    #[test]
+    fn quarterly_aligns_to_calendar_quarters() {
+        assert_eq!(
+            Granularity::Quarterly.align(at(2024, 2, 29)),
+            at(2024, 1, 1)
+        );
+        assert_eq!(Granularity::Quarterly.align(at(2024, 4, 1)), at(2024, 4, 1));
+        assert_eq!(
+            Granularity::Quarterly.align(at(2024, 6, 30)),
+            at(2024, 4, 1)
+        );
+        let new_years_eve = Utc.with_ymd_and_hms(2024, 12, 31, 23, 59, 59).unwrap();
+        assert_eq!(Granularity::Quarterly.align(new_years_eve), at(2024, 10, 1));
+        assert_eq!(Granularity::Quarterly.next(at(2024, 10, 1)), at(2025, 1, 1));
+        assert_eq!(
+            starts(Granularity::Quarterly, at(2024, 11, 15), at(2025, 4, 1)),
+            [at(2024, 10, 1), at(2025, 1, 1), at(2025, 4, 1)].map(|d| d.date_naive())
+        );
+    }
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    /// Pseudo random nodes created between 2020 and 2023, plus a node created exactly on every
+    /// monthly period start.
+    fn nodes() -> Vec<Node> {
+        let from = at(2020, 1, 1).timestamp();
+        let random = (0..600u64).map(|i| {
+            let x = i
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407)
+                >> 16;
+            (from + (x % (3 * 365 * DAY as u64)) as i64, x)
+        });
+        let on_start = Granularity::Monthly
+            .periods(at(2020, 1, 1), at(2023, 1, 1))
+            .into_iter()
+            .map(|p| (p.start.timestamp(), p.start.timestamp() as u64 / DAY as u64));
+        random
+            .chain(on_start)
+            .enumerate()
+            .map(|(i, (created, x))| {
+                let certification = [None, Some(NodeCertification::Diy)];
+                let status = [
+                    None,
+                    Some(NodeStatus::Up),
+                    Some(NodeStatus::Standby),
+                    Some(NodeStatus::Down),
+                ];
+                Node::new(
+                    i as u32,
+                    (x % 20) as u32,
+                    created,
+                    Resources::new(x % 7 + 1, x % 5 * 1024, x % 3 * 2048, x % 11),
+                )
+                .with_resources_used((i % 3 != 0).then(|| Resources::new(x % 2, 0, 1, 0)))
+                .with_certification(
+                    certification
+                        .get(i % 3)
+                        .copied()
+                        .unwrap_or(Some(NodeCertification::Certified)),
+                )
+                .with_activity(
+                    (i % 5 != 0).then_some(created + (x % 400) as i64 * DAY),
+                    status[i % 4],
+                )
+            })
+            .collect()
+    }
+
+    /// Aggregate every period separately by filtering all nodes, as reference for the sweep.
+    fn naive_aggregate(nodes: &[Node], periods: &[Period], staleness: i64) -> Vec<PeriodAggregate> {
+        let totals = |ts: i64| {
+            let counted = nodes.iter().filter(|n| n.created() < ts);
+            let mut totals = Totals::default();
+            let mut farms = HashSet::new();
+            for node in counted {
+                farms.insert(node.farm_id());
+                totals.node_count += 1;
+                totals.resources += node.resources_total();
+                if let Some(used) = node.resources_used() {
+                    totals.used_resources += used;
+                    totals.reporting_usage.add(node.resources_total());
+                }
+            }
+            totals.farms = farms.len() as u64;
+            totals
+        };
+        let subset = |f: &dyn Fn(&Node) -> bool| {
+            let mut totals = NodeTotals::default();
+            for node in nodes.iter().filter(|n| f(n)) {
+                totals.add(node.resources_total());
+            }
+            totals
+        };
+
+        periods
+            .iter()
+            .map(|period| {
+                let ts = period.start.timestamp();
+                let start = totals(ts);
+                let end = totals(period.end.timestamp());
+                let month_ago = totals((period.start - Months::new(1)).timestamp());
+                let year_ago = totals((period.start - Months::new(12)).timestamp());
+                PeriodAggregate {
+                    start: period.start,
+                    end: period.end,
+                    node_count: start.node_count,
+                    farms: start.farms,
+                    resources: start.resources,
+                    used_resources: start.used_resources,
+                    reporting_usage: start.reporting_usage,
+                    new_nodes: end.node_count - start.node_count,
+                    new_farms: end.farms - start.farms,
+                    new_resources: end.resources - start.resources,
+                    mom_growth: growth(month_ago.node_count, start.node_count),
+                    yoy_growth: growth(year_ago.node_count, start.node_count),
+                    certification: NodeCertification::ALL
+                        .map(|c| subset(&|n| n.created() < ts && n.certification() == Some(c))),
+                    activity: NodeStatus::ALL
+                        .map(|s| subset(&|n| n.status_at(ts, staleness) == Some(s))),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sweep_matches_naive_aggregate() {
+        let nodes = nodes();
+        for granularity in Granularity::ALL {
+            let periods = granularity.periods(at(2019, 12, 1), at(2023, 6, 1));
+            for staleness in [0, DAY, 30 * DAY] {
+                assert_eq!(
+                    aggregate(
+                        &nodes,
+                        &periods,
+                        Some(Duration::from_secs(staleness as u64))
+                    ),
+                    naive_aggregate(&nodes, &periods, staleness),
+                    "{granularity}, staleness {staleness}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn nodes_are_only_split_by_status_with_a_staleness() {
+        let nodes = nodes();
+        let periods = Granularity::Yearly.periods(at(2019, 1, 1), at(2023, 1, 1));
+        let with = aggregate(&nodes, &periods, Some(DEFAULT_STALENESS));
+        let without = aggregate(&nodes, &periods, None);
+
+        assert!(with
+            .iter()
+            .any(|a| a.by_status(NodeStatus::Up).node_count() > 0));
+        for (with, without) in with.iter().zip(&without) {
+            assert_eq!(with.node_count(), without.node_count());
+            for status in NodeStatus::ALL {
+                assert_eq!(without.by_status(status), &NodeTotals::default());
+            }
+        }
+    }
+
+    #[test]
+    fn nodes_created_on_period_start_count_in_next_period() {
+        let start = at(2021, 3, 1).timestamp();
+        let resources = Resources::new(1, 0, 0, 0);
+        let nodes = [
+            Node::new(1, 1, start - 1, resources),
+            Node::new(2, 2, start, resources),
+            Node::new(3, 2, start + 1, resources),
+        ];
+        let periods = Granularity::Monthly.periods(at(2021, 2, 1), at(2021, 4, 1));
+        let aggregates = aggregate(&nodes, &periods, Some(DEFAULT_STALENESS));
+
+        let counts = aggregates
+            .iter()
+            .map(|a| (a.node_count(), a.farms(), a.new_nodes(), a.new_farms()))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [(0, 0, 1, 1), (1, 1, 2, 1), (3, 2, 0, 0)]);
+        assert_eq!(aggregates[1].new_resources().cru(), 2);
+        assert_eq!(aggregates, naive_aggregate(&nodes, &periods, DAY));
+    }
+
+    #[test]
+    fn growth_compares_to_month_and_year_before() {
+        let resources = Resources::default();
+        let nodes = [
+            Node::new(1, 1, at(2020, 1, 15).timestamp(), resources),
+            Node::new(2, 1, at(2020, 12, 15).timestamp(), resources),
+            Node::new(3, 1, at(2021, 1, 1).timestamp(), resources),
+            Node::new(4, 1, at(2021, 1, 20).timestamp(), resources),
+        ];
+        let periods = Granularity::Monthly.periods(at(2021, 2, 1), at(2021, 2, 1));
+        let aggregate = &aggregate(&nodes, &periods, Some(DEFAULT_STALENESS))[0];
+
+        assert_eq!(aggregate.node_count(), 4);
+        // 2 nodes on 2021-01-01, as the node created on it isn't counted yet.
+        assert_eq!(aggregate.mom_growth(), Some(100.));
+        // 1 node on 2020-02-01.
+        assert_eq!(aggregate.yoy_growth(), Some(300.));
+
+        let periods = Granularity::Monthly.periods(at(2020, 1, 1), at(2020, 2, 1));
+        let aggregates = super::aggregate(&nodes, &periods, Some(DEFAULT_STALENESS));
+        assert_eq!(aggregates[0].mom_growth(), None);
+        assert_eq!(aggregates[1].mom_growth(), None);
+    }
+
+    #[test]
+    fn sweep_matches_naive_aggregate_by_farm() {
+        let nodes = nodes();
+        let periods = Granularity::Monthly.periods(at(2019, 12, 1), at(2023, 6, 1));
+
+        let mut expected = Vec::new();
+        for period in &periods {
+            let mut farms = BTreeMap::<u32, Totals>::new();
+            for node in nodes
+                .iter()
+                .filter(|n| n.created() < period.start.timestamp())
+            {
+                let totals = farms.entry(node.farm_id()).or_default();
+                totals.node_count += 1;
+                totals.resources += node.resources_total();
+                if let Some(used) = node.resources_used() {
+                    totals.used_resources += used;
+                    totals.reporting_usage.add(node.resources_total());
+                }
+            }
+            expected.extend(farms.into_iter().map(|(farm_id, totals)| FarmAggregate {
+                start: period.start,
+                end: period.end,
+                farm_id,
+                node_count: totals.node_count,
+                resources: totals.resources,
+                used_resources: totals.used_resources,
+                reporting_usage: totals.reporting_usage,
+            }));
+        }
+
+        assert_eq!(aggregate_by_farm(&nodes, &periods), expected);
+    }
+
+    #[test]
+    fn periods_are_contiguous() {
+        for granularity in Granularity::ALL {
+            let periods = granularity.periods(at(2023, 12, 20), at(2025, 2, 3));
+            assert!(periods.first().unwrap().start <= at(2023, 12, 20));
+            assert!(periods.last().unwrap().start <= at(2025, 2, 3));
+            assert!(periods.last().unwrap().end > at(2025, 2, 3));
+            for pair in periods.windows(2) {
+                assert_eq!(pair[0].end, pair[1].start, "{granularity}");
+            }
+        }
+    }
+}
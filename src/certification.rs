@@ -0,0 +1,47 @@
+/// How a node was certified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NodeCertification {
+    /// A self built node.
+    Diy,
+    /// A node built by a certified vendor.
+    Certified,
+}
+
+impl NodeCertification {
+    /// All node certifications.
+    pub const ALL: [NodeCertification; 2] = [NodeCertification::Diy, NodeCertification::Certified];
+
+    /// Short lowercase name of the certification.
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeCertification::Diy => "diy",
+            NodeCertification::Certified => "certified",
+        }
+    }
+}
+
+/// The certification level of a farm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FarmCertification {
+    /// A farm without certification.
+    NotCertified,
+    /// A gold certified farm.
+    Gold,
+}
+
+impl FarmCertification {
+    /// All farm certifications.
+    pub const ALL: [FarmCertification; 2] =
+        [FarmCertification::NotCertified, FarmCertification::Gold];
+
+    /// Short lowercase name of the certification.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FarmCertification::NotCertified => "not_certified",
+            FarmCertification::Gold => "gold",
+        }
+    }
+}
+
+name_impls!(NodeCertification, "node certification", serde);
+name_impls!(FarmCertification, "farm certification", serde);
@@ -0,0 +1,24 @@
+use node_counter::{write_aggregates, Format, NodeCounterError};
+
+use crate::{GlobalArgs, COUNT_OUTPUT_FILE};
+
+pub async fn run(global: &GlobalArgs) -> Result<(), NodeCounterError> {
+    global.check_format("count", Format::supports_aggregates)?;
+    let (counter, nodes) = global.nodes().await?;
+    let rows = counter.aggregate(&nodes);
+
+    global
+        .open_output(Some(&format!(
+            "{COUNT_OUTPUT_FILE}.{}",
+            global.format().extension()
+        )))?
+        .write_with(|w| {
+            write_aggregates(
+                w,
+                global.format(),
+                &counter.metadata(),
+                global.columns(),
+                &rows,
+            )
+        })
+}
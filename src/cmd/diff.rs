@@ -0,0 +1,79 @@
+use std::{convert::Infallible, path::PathBuf, str::FromStr};
+
+use clap::ValueEnum;
+use node_counter::{
+    diff, read_nodes, write_rows, Format, Metadata, Node, NodeCounterError, SnapshotStore,
+};
+
+use crate::GlobalArgs;
+
+#[derive(clap::Args)]
+pub struct DiffArgs {
+    /// Old set of nodes: a file created with `fetch --format json` or `--save-raw`, or
+    /// `snapshot:<id>` for a snapshot in the store.
+    old: DiffInput,
+    /// New set of nodes: a file created with `fetch --format json` or `--save-raw`, or
+    /// `snapshot:<id>` for a snapshot in the store.
+    new: DiffInput,
+    /// What to write: every changed node, changes per farm, or the totals.
+    #[arg(long, value_enum, default_value_t = DiffView::Nodes)]
+    by: DiffView,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DiffView {
+    Nodes,
+    Farms,
+    Totals,
+}
+
+/// A set of nodes to compare.
+#[derive(Clone)]
+enum DiffInput {
+    File(PathBuf),
+    Snapshot(i64),
+}
+
+impl FromStr for DiffInput {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.strip_prefix("snapshot:").map(str::parse) {
+            Some(Ok(id)) => DiffInput::Snapshot(id),
+            _ => DiffInput::File(PathBuf::from(s)),
+        })
+    }
+}
+
+impl DiffInput {
+    fn load(&self, global: &GlobalArgs) -> Result<Vec<Node>, NodeCounterError> {
+        match self {
+            DiffInput::File(path) => read_nodes(path),
+            DiffInput::Snapshot(id) => {
+                let Some(store) = &global.store else {
+                    return Err(NodeCounterError::MissingArgument {
+                        argument: "--store",
+                        required_by: "comparing snapshots",
+                    });
+                };
+                let store = SnapshotStore::open(store)?;
+                store.load(&store.snapshot(*id)?)
+            }
+        }
+    }
+}
+
+pub fn run(global: &GlobalArgs, args: DiffArgs) -> Result<(), NodeCounterError> {
+    global.check_format("diff", Format::supports_rows)?;
+    let old = args.old.load(global)?;
+    let new = args.new.load(global)?;
+    let diff = diff(&old, &new);
+
+    let counter = global.counter()?;
+    let metadata = Metadata::new(global.network, counter.selected_endpoint());
+    global.open_output(None)?.write_with(|w| match args.by {
+        DiffView::Nodes => write_rows(w, global.format(), &metadata, diff.changes()),
+        DiffView::Farms => write_rows(w, global.format(), &metadata, &diff.by_farm()),
+        DiffView::Totals => write_rows(w, global.format(), &metadata, &[diff.summary()]),
+    })
+}
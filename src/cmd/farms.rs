@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+
+use node_counter::{
+    aggregate_by_farm, largest_farms, write_farm_aggregates, Format, Layout, NodeCounterError,
+};
+
+use crate::GlobalArgs;
+
+#[derive(clap::Args)]
+pub struct FarmsArgs {
+    /// Row layout: one row per period and farm (long), or one row per period with columns per
+    /// farm (wide).
+    #[arg(long, default_value_t = Layout::Long)]
+    layout: Layout,
+    /// Only include the farms with the most nodes in the last period.
+    #[arg(long)]
+    top: Option<usize>,
+    /// Farm to include, also when it isn't in the top. Can be repeated. Without `--top`, only
+    /// these farms are included.
+    #[arg(long = "farm")]
+    farms: Vec<u32>,
+}
+
+pub async fn run(global: &GlobalArgs, args: FarmsArgs) -> Result<(), NodeCounterError> {
+    global.check_format("farms", Format::supports_rows)?;
+    let (counter, nodes) = global.nodes().await?;
+    let periods = counter.periods(&nodes);
+    let mut rows = aggregate_by_farm(&nodes, &periods);
+
+    if args.top.is_some() || !args.farms.is_empty() {
+        let mut selected = args.farms.iter().copied().collect::<HashSet<_>>();
+        if let Some(top) = args.top {
+            selected.extend(largest_farms(&rows, top));
+        }
+        rows.retain(|row| selected.contains(&row.farm_id()));
+    }
+
+    global.open_output(None)?.write_with(|w| {
+        write_farm_aggregates(
+            w,
+            global.format(),
+            &counter.metadata(),
+            args.layout,
+            global.columns(),
+            &periods,
+            &rows,
+        )
+    })
+}
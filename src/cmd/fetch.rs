@@ -0,0 +1,11 @@
+use node_counter::{write_nodes, NodeCounterError};
+
+use crate::GlobalArgs;
+
+pub async fn run(global: &GlobalArgs) -> Result<(), NodeCounterError> {
+    let (counter, nodes) = global.nodes().await?;
+
+    global
+        .open_output(None)?
+        .write_with(|w| write_nodes(w, global.format(), &counter.metadata(), &nodes))
+}
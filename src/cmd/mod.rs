@@ -0,0 +1,10 @@
+pub mod count;
+pub mod diff;
+pub mod farms;
+pub mod fetch;
+pub mod map;
+pub mod regions;
+pub mod report;
+pub mod serve;
+pub mod snapshots;
+pub mod verify;
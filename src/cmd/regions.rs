@@ -0,0 +1,20 @@
+use node_counter::{write_region_aggregates, Format, NodeCounterError, Region};
+
+use crate::GlobalArgs;
+
+#[derive(clap::Args)]
+pub struct RegionsArgs {
+    /// Level to group nodes at (country, continent).
+    #[arg(long, default_value_t = Region::Country)]
+    by: Region,
+}
+
+pub async fn run(global: &GlobalArgs, args: RegionsArgs) -> Result<(), NodeCounterError> {
+    global.check_format("regions", Format::supports_rows)?;
+    let (counter, nodes) = global.nodes().await?;
+    let rows = counter.aggregate_by_region(&nodes, args.by);
+
+    global.open_output(None)?.write_with(|w| {
+        write_region_aggregates(w, global.format(), &counter.metadata(), args.by, &rows)
+    })
+}
@@ -0,0 +1,107 @@
+use std::io::{self, Write};
+
+use node_counter::{NodeCounterError, PeriodAggregate};
+
+use crate::GlobalArgs;
+
+pub async fn run(global: &GlobalArgs) -> Result<(), NodeCounterError> {
+    // The report is plain text, use `count` for the node counts in another format.
+    if let Some(format) = global.format {
+        return Err(NodeCounterError::UnsupportedFormat {
+            format,
+            command: "report",
+        });
+    }
+
+    let (counter, nodes) = global.nodes().await?;
+    let rows = counter.aggregate(&nodes);
+
+    global
+        .open_output(None)?
+        .write_with(|w| write_report(w, &counter.selected_network().to_string(), &rows))
+}
+
+/// Write a human readable summary comparing the first and last period.
+fn write_report(w: &mut dyn Write, network: &str, rows: &[PeriodAggregate]) -> io::Result<()> {
+    let (Some(first), Some(last)) = (rows.first(), rows.last()) else {
+        return writeln!(w, "No periods in the selected time range");
+    };
+
+    writeln!(w, "Network: {network}")?;
+    writeln!(
+        w,
+        "Period:  {} to {}",
+        first.start().date_naive(),
+        last.start().date_naive()
+    )?;
+    writeln!(w)?;
+    write_line(w, "Nodes", first.node_count(), last.node_count(), |v| {
+        v.to_string()
+    })?;
+    write_line(w, "Farms with nodes", first.farms(), last.farms(), |v| {
+        v.to_string()
+    })?;
+    write_line(
+        w,
+        "CRU",
+        first.resources().cru(),
+        last.resources().cru(),
+        |v| v.to_string(),
+    )?;
+    write_line(
+        w,
+        "MRU",
+        first.resources().mru(),
+        last.resources().mru(),
+        format_bytes,
+    )?;
+    write_line(
+        w,
+        "SRU",
+        first.resources().sru(),
+        last.resources().sru(),
+        format_bytes,
+    )?;
+    write_line(
+        w,
+        "HRU",
+        first.resources().hru(),
+        last.resources().hru(),
+        format_bytes,
+    )
+}
+
+fn write_line(
+    w: &mut dyn Write,
+    label: &str,
+    first: u64,
+    last: u64,
+    fmt: impl Fn(u64) -> String,
+) -> io::Result<()> {
+    let growth = if first == 0 {
+        String::from("n/a")
+    } else {
+        format!(
+            "{:+.1}%",
+            (last as f64 - first as f64) / first as f64 * 100.
+        )
+    };
+    writeln!(
+        w,
+        "{label:<18} {:>12} -> {:>12} ({growth})",
+        fmt(first),
+        fmt(last)
+    )
+}
+
+/// Format an amount of bytes with a binary unit.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024. && unit < UNITS.len() - 1 {
+        value /= 1024.;
+        unit += 1;
+    }
+    format!("{value:.2} {}", UNITS[unit])
+}
@@ -0,0 +1,126 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use node_counter::{write_aggregates, Columns, Format, NodeCounter, NodeCounterError};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::RwLock,
+};
+
+use crate::GlobalArgs;
+
+#[derive(clap::Args)]
+pub struct ServeArgs {
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+    /// Interval in seconds between refreshes of the node counts.
+    #[arg(long, default_value_t = 3600)]
+    refresh: u64,
+}
+
+pub async fn run(global: &GlobalArgs, args: ServeArgs) -> Result<(), NodeCounterError> {
+    // Every refresh would fail to write the counts, so reject the format before listening.
+    global.check_format("serve", Format::supports_aggregates)?;
+
+    let counter = global.counter()?;
+    let listener = TcpListener::bind(args.listen)
+        .await
+        .map_err(|source| NodeCounterError::Io {
+            path: args.listen.to_string().into(),
+            source,
+        })?;
+
+    let body = Arc::new(RwLock::new(None));
+    tokio::spawn(refresh(
+        counter,
+        global.format(),
+        global.columns(),
+        Duration::from_secs(args.refresh.max(1)),
+        body.clone(),
+    ));
+
+    log::info!("Listening on {}", args.listen);
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::warn!("Failed to accept connection: {e}");
+                continue;
+            }
+        };
+        let body = body.clone();
+        let format = global.format();
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, format, body).await {
+                log::warn!("Failed to handle connection: {e}");
+            }
+        });
+    }
+}
+
+/// Periodically recount the nodes, keeping the last good body if a refresh fails.
+async fn refresh(
+    counter: NodeCounter,
+    format: Format,
+    columns: Columns,
+    interval: Duration,
+    body: Arc<RwLock<Option<Vec<u8>>>>,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match counter.count().await {
+            Ok(rows) => {
+                let mut buf = Vec::new();
+                // Writing to a Vec only fails if the format doesn't support aggregates.
+                match write_aggregates(&mut buf, format, &counter.metadata(), columns, &rows) {
+                    Ok(()) => *body.write().await = Some(buf),
+                    Err(e) => log::error!("Failed to write node counts: {e}"),
+                }
+            }
+            Err(e) => log::error!("Failed to refresh node counts: {e}"),
+        }
+    }
+}
+
+/// Handle a single HTTP request, only `GET /` is supported.
+async fn handle(
+    mut stream: TcpStream,
+    format: Format,
+    body: Arc<RwLock<Option<Vec<u8>>>>,
+) -> std::io::Result<()> {
+    let mut buf = [0; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+
+    let (status, content_type, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/")) => match &*body.read().await {
+            Some(body) => ("200 OK", format.content_type(), body.clone()),
+            None => (
+                "503 Service Unavailable",
+                "text/plain",
+                b"Node counts not available yet\n".to_vec(),
+            ),
+        },
+        (Some("GET"), Some(_)) => ("404 Not Found", "text/plain", b"Not found\n".to_vec()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Method not allowed\n".to_vec(),
+        ),
+    };
+
+    stream
+        .write_all(
+            format!(
+                "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .as_bytes(),
+        )
+        .await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,45 @@
+use node_counter::{write_rows, Format, Metadata, NodeCounterError, Snapshot, SnapshotStore};
+use serde::Serialize;
+
+use crate::GlobalArgs;
+
+/// A stored snapshot, as written to the output.
+#[derive(Default, Serialize)]
+struct SnapshotRow {
+    id: i64,
+    #[serde(rename = "taken at")]
+    taken_at: String,
+    network: String,
+    endpoint: String,
+    #[serde(rename = "node count")]
+    node_count: u64,
+}
+
+impl From<&Snapshot> for SnapshotRow {
+    fn from(snapshot: &Snapshot) -> Self {
+        Self {
+            id: snapshot.id(),
+            taken_at: snapshot.taken_at().to_rfc3339(),
+            network: snapshot.network().to_string(),
+            endpoint: snapshot.endpoint().to_string(),
+            node_count: snapshot.node_count(),
+        }
+    }
+}
+
+pub fn run(global: &GlobalArgs) -> Result<(), NodeCounterError> {
+    global.check_format("snapshots", Format::supports_rows)?;
+    let Some(store) = &global.store else {
+        return Err(NodeCounterError::MissingArgument {
+            argument: "--store",
+            required_by: "the snapshots command",
+        });
+    };
+    let snapshots = SnapshotStore::open(store)?.snapshots()?;
+    let rows = snapshots.iter().map(SnapshotRow::from).collect::<Vec<_>>();
+
+    let metadata = Metadata::new(global.network, store.display().to_string());
+    global
+        .open_output(None)?
+        .write_with(|w| write_rows(w, global.format(), &metadata, &rows))
+}
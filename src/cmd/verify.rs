@@ -0,0 +1,70 @@
+use clap::ValueEnum;
+use node_counter::{
+    verify, write_rows, Format, Metadata, NodeCounter, NodeCounterError, Source, Tolerance,
+};
+
+use crate::GlobalArgs;
+
+#[derive(clap::Args)]
+pub struct VerifyArgs {
+    /// Custom graphql endpoint, overriding the graphql endpoint of the network.
+    #[arg(long)]
+    graphql_endpoint: Option<String>,
+    /// Custom Grid Proxy endpoint, overriding the Grid Proxy endpoint of the network.
+    #[arg(long)]
+    grid_proxy_endpoint: Option<String>,
+    /// Maximum amount of mismatched nodes, as amount or as percentage like `0.5%`. If more nodes
+    /// differ, the command fails after writing its output.
+    #[arg(long, default_value_t = Tolerance::Nodes(0))]
+    tolerance: Tolerance,
+    /// What to write: every discrepancy, or the totals.
+    #[arg(long, value_enum, default_value_t = VerifyView::Nodes)]
+    by: VerifyView,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum VerifyView {
+    Nodes,
+    Totals,
+}
+
+pub async fn run(global: &GlobalArgs, args: VerifyArgs) -> Result<(), NodeCounterError> {
+    global.check_format("verify", Format::supports_rows)?;
+    let counter = |source: Source, endpoint: &Option<String>| -> Result<NodeCounter, _> {
+        // Fallback endpoints are specific to a single source, so they aren't used here.
+        let counter = global
+            .counter()?
+            .source(source)
+            .fallback_endpoints(Vec::new());
+        Ok(counter.endpoint(endpoint.as_deref().unwrap_or(source.url(global.network))))
+    };
+    let graphql = counter(Source::GraphQL, &args.graphql_endpoint)?;
+    let grid_proxy = counter(Source::GridProxy, &args.grid_proxy_endpoint)?;
+
+    let (graphql_nodes, grid_proxy_nodes) =
+        tokio::try_join!(graphql.fetch_nodes(), grid_proxy.fetch_nodes())?;
+    let verification = verify(&graphql_nodes, &grid_proxy_nodes);
+    let summary = verification.summary();
+    log::info!(
+        "graphql has {} nodes, grid proxy has {} nodes, {} nodes differ",
+        graphql_nodes.len(),
+        grid_proxy_nodes.len(),
+        summary.mismatched_nodes()
+    );
+
+    let metadata = Metadata::new(global.network, graphql.selected_endpoint());
+    global.open_output(None)?.write_with(|w| match args.by {
+        VerifyView::Nodes => {
+            write_rows(w, global.format(), &metadata, verification.discrepancies())
+        }
+        VerifyView::Totals => write_rows(w, global.format(), &metadata, &[summary]),
+    })?;
+
+    if !verification.within(args.tolerance) {
+        return Err(NodeCounterError::VerifyFailed {
+            mismatched: verification.mismatched_nodes(),
+            tolerance: args.tolerance,
+        });
+    }
+    Ok(())
+}
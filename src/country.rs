@@ -0,0 +1,539 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use serde::{Serialize, Serializer};
+
+/// A continent, as used to group countries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Continent {
+    Africa,
+    Antarctica,
+    Asia,
+    Europe,
+    NorthAmerica,
+    Oceania,
+    SouthAmerica,
+}
+
+impl Continent {
+    /// All continents.
+    pub const ALL: [Continent; 7] = [
+        Continent::Africa,
+        Continent::Antarctica,
+        Continent::Asia,
+        Continent::Europe,
+        Continent::NorthAmerica,
+        Continent::Oceania,
+        Continent::SouthAmerica,
+    ];
+
+    /// Short lowercase name of the continent.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Continent::Africa => "africa",
+            Continent::Antarctica => "antarctica",
+            Continent::Asia => "asia",
+            Continent::Europe => "europe",
+            Continent::NorthAmerica => "north_america",
+            Continent::Oceania => "oceania",
+            Continent::SouthAmerica => "south_america",
+        }
+    }
+}
+
+name_impls!(Continent, "continent");
+
+/// A country from ISO 3166-1.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Country {
+    code: &'static str,
+    name: &'static str,
+    continent: Continent,
+}
+
+impl Country {
+    /// Look up a country by its ISO 3166-1 alpha-2 code, its name, or a common alternative
+    /// spelling. Case, accents, punctuation and whitespace are ignored.
+    pub fn find(name: &str) -> Option<&'static Country> {
+        let name = name.trim();
+        if name.len() == 2 && name.chars().all(|c| c.is_ascii_alphabetic()) {
+            if let Some(country) = COUNTRIES
+                .iter()
+                .find(|country| country.code.eq_ignore_ascii_case(name))
+            {
+                return Some(country);
+            }
+        }
+        index().get(&normalize(name)).copied()
+    }
+
+    /// ISO 3166-1 alpha-2 code of the country.
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// Short english name of the country.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The continent the country is in.
+    pub fn continent(&self) -> Continent {
+        self.continent
+    }
+}
+
+impl Serialize for Country {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(self.code)
+    }
+}
+
+/// Normalized country names and aliases, mapped to their country.
+fn index() -> &'static HashMap<String, &'static Country> {
+    static INDEX: OnceLock<HashMap<String, &'static Country>> = OnceLock::new();
+    INDEX.get_or_init(|| {
+        let by_code = |code| COUNTRIES.iter().find(|c| c.code == code).unwrap();
+        COUNTRIES
+            .iter()
+            .map(|country| (normalize(country.name), country))
+            .chain(
+                ALIASES
+                    .iter()
+                    .map(|(code, alias)| (normalize(alias), by_code(*code))),
+            )
+            .collect()
+    })
+}
+
+/// Lowercase a name, fold accented letters to ascii, and drop everything but letters and digits
+/// and a leading "the".
+fn normalize(name: &str) -> String {
+    let folded = name
+        .chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' => 'a',
+            'ç' | 'č' | 'ć' => 'c',
+            'è' | 'é' | 'ê' | 'ë' | 'ě' | 'ē' => 'e',
+            'ì' | 'í' | 'î' | 'ï' => 'i',
+            'ñ' | 'ń' => 'n',
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ő' => 'o',
+            'ù' | 'ú' | 'û' | 'ü' | 'ů' | 'ű' => 'u',
+            'ý' | 'ÿ' => 'y',
+            'ş' | 'š' | 'ș' => 's',
+            'ț' | 'ţ' => 't',
+            'ž' | 'ź' | 'ż' => 'z',
+            'ł' => 'l',
+            c => c,
+        })
+        .collect::<String>();
+    let folded = folded.trim_start();
+    let folded = folded.strip_prefix("the ").unwrap_or(folded);
+    folded
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect()
+}
+
+const fn country(code: &'static str, name: &'static str, continent: Continent) -> Country {
+    Country {
+        code,
+        name,
+        continent,
+    }
+}
+
+const AF: Continent = Continent::Africa;
+const AN: Continent = Continent::Antarctica;
+const AS: Continent = Continent::Asia;
+const EU: Continent = Continent::Europe;
+const NA: Continent = Continent::NorthAmerica;
+const OC: Continent = Continent::Oceania;
+const SA: Continent = Continent::SouthAmerica;
+
+/// All ISO 3166-1 countries, and Kosovo under its commonly used user-assigned code.
+/// Transcontinental countries are assigned to the continent they are most commonly grouped with.
+static COUNTRIES: &[Country] = &[
+    country("AD", "Andorra", EU),
+    country("AE", "United Arab Emirates", AS),
+    country("AF", "Afghanistan", AS),
+    country("AG", "Antigua and Barbuda", NA),
+    country("AI", "Anguilla", NA),
+    country("AL", "Albania", EU),
+    country("AM", "Armenia", AS),
+    country("AO", "Angola", AF),
+    country("AQ", "Antarctica", AN),
+    country("AR", "Argentina", SA),
+    country("AS", "American Samoa", OC),
+    country("AT", "Austria", EU),
+    country("AU", "Australia", OC),
+    country("AW", "Aruba", NA),
+    country("AX", "Åland Islands", EU),
+    country("AZ", "Azerbaijan", AS),
+    country("BA", "Bosnia and Herzegovina", EU),
+    country("BB", "Barbados", NA),
+    country("BD", "Bangladesh", AS),
+    country("BE", "Belgium", EU),
+    country("BF", "Burkina Faso", AF),
+    country("BG", "Bulgaria", EU),
+    country("BH", "Bahrain", AS),
+    country("BI", "Burundi", AF),
+    country("BJ", "Benin", AF),
+    country("BL", "Saint Barthélemy", NA),
+    country("BM", "Bermuda", NA),
+    country("BN", "Brunei Darussalam", AS),
+    country("BO", "Bolivia", SA),
+    country("BQ", "Bonaire, Sint Eustatius and Saba", NA),
+    country("BR", "Brazil", SA),
+    country("BS", "Bahamas", NA),
+    country("BT", "Bhutan", AS),
+    country("BV", "Bouvet Island", AN),
+    country("BW", "Botswana", AF),
+    country("BY", "Belarus", EU),
+    country("BZ", "Belize", NA),
+    country("CA", "Canada", NA),
+    country("CC", "Cocos (Keeling) Islands", AS),
+    country("CD", "Congo, Democratic Republic of the", AF),
+    country("CF", "Central African Republic", AF),
+    country("CG", "Congo", AF),
+    country("CH", "Switzerland", EU),
+    country("CI", "Côte d'Ivoire", AF),
+    country("CK", "Cook Islands", OC),
+    country("CL", "Chile", SA),
+    country("CM", "Cameroon", AF),
+    country("CN", "China", AS),
+    country("CO", "Colombia", SA),
+    country("CR", "Costa Rica", NA),
+    country("CU", "Cuba", NA),
+    country("CV", "Cabo Verde", AF),
+    country("CW", "Curaçao", NA),
+    country("CX", "Christmas Island", AS),
+    country("CY", "Cyprus", EU),
+    country("CZ", "Czechia", EU),
+    country("DE", "Germany", EU),
+    country("DJ", "Djibouti", AF),
+    country("DK", "Denmark", EU),
+    country("DM", "Dominica", NA),
+    country("DO", "Dominican Republic", NA),
+    country("DZ", "Algeria", AF),
+    country("EC", "Ecuador", SA),
+    country("EE", "Estonia", EU),
+    country("EG", "Egypt", AF),
+    country("EH", "Western Sahara", AF),
+    country("ER", "Eritrea", AF),
+    country("ES", "Spain", EU),
+    country("ET", "Ethiopia", AF),
+    country("FI", "Finland", EU),
+    country("FJ", "Fiji", OC),
+    country("FK", "Falkland Islands", SA),
+    country("FM", "Micronesia", OC),
+    country("FO", "Faroe Islands", EU),
+    country("FR", "France", EU),
+    country("GA", "Gabon", AF),
+    country("GB", "United Kingdom", EU),
+    country("GD", "Grenada", NA),
+    country("GE", "Georgia", AS),
+    country("GF", "French Guiana", SA),
+    country("GG", "Guernsey", EU),
+    country("GH", "Ghana", AF),
+    country("GI", "Gibraltar", EU),
+    country("GL", "Greenland", NA),
+    country("GM", "Gambia", AF),
+    country("GN", "Guinea", AF),
+    country("GP", "Guadeloupe", NA),
+    country("GQ", "Equatorial Guinea", AF),
+    country("GR", "Greece", EU),
+    country("GS", "South Georgia and the South Sandwich Islands", AN),
+    country("GT", "Guatemala", NA),
+    country("GU", "Guam", OC),
+    country("GW", "Guinea-Bissau", AF),
+    country("GY", "Guyana", SA),
+    country("HK", "Hong Kong", AS),
+    country("HM", "Heard Island and McDonald Islands", AN),
+    country("HN", "Honduras", NA),
+    country("HR", "Croatia", EU),
+    country("HT", "Haiti", NA),
+    country("HU", "Hungary", EU),
+    country("ID", "Indonesia", AS),
+    country("IE", "Ireland", EU),
+    country("IL", "Israel", AS),
+    country("IM", "Isle of Man", EU),
+    country("IN", "India", AS),
+    country("IO", "British Indian Ocean Territory", AS),
+    country("IQ", "Iraq", AS),
+    country("IR", "Iran", AS),
+    country("IS", "Iceland", EU),
+    country("IT", "Italy", EU),
+    country("JE", "Jersey", EU),
+    country("JM", "Jamaica", NA),
+    country("JO", "Jordan", AS),
+    country("JP", "Japan", AS),
+    country("KE", "Kenya", AF),
+    country("KG", "Kyrgyzstan", AS),
+    country("KH", "Cambodia", AS),
+    country("KI", "Kiribati", OC),
+    country("KM", "Comoros", AF),
+    country("KN", "Saint Kitts and Nevis", NA),
+    country("KP", "North Korea", AS),
+    country("KR", "South Korea", AS),
+    country("KW", "Kuwait", AS),
+    country("KY", "Cayman Islands", NA),
+    country("KZ", "Kazakhstan", AS),
+    country("LA", "Laos", AS),
+    country("LB", "Lebanon", AS),
+    country("LC", "Saint Lucia", NA),
+    country("LI", "Liechtenstein", EU),
+    country("LK", "Sri Lanka", AS),
+    country("LR", "Liberia", AF),
+    country("LS", "Lesotho", AF),
+    country("LT", "Lithuania", EU),
+    country("LU", "Luxembourg", EU),
+    country("LV", "Latvia", EU),
+    country("LY", "Libya", AF),
+    country("MA", "Morocco", AF),
+    country("MC", "Monaco", EU),
+    country("MD", "Moldova", EU),
+    country("ME", "Montenegro", EU),
+    country("MF", "Saint Martin", NA),
+    country("MG", "Madagascar", AF),
+    country("MH", "Marshall Islands", OC),
+    country("MK", "North Macedonia", EU),
+    country("ML", "Mali", AF),
+    country("MM", "Myanmar", AS),
+    country("MN", "Mongolia", AS),
+    country("MO", "Macao", AS),
+    country("MP", "Northern Mariana Islands", OC),
+    country("MQ", "Martinique", NA),
+    country("MR", "Mauritania", AF),
+    country("MS", "Montserrat", NA),
+    country("MT", "Malta", EU),
+    country("MU", "Mauritius", AF),
+    country("MV", "Maldives", AS),
+    country("MW", "Malawi", AF),
+    country("MX", "Mexico", NA),
+    country("MY", "Malaysia", AS),
+    country("MZ", "Mozambique", AF),
+    country("NA", "Namibia", AF),
+    country("NC", "New Caledonia", OC),
+    country("NE", "Niger", AF),
+    country("NF", "Norfolk Island", OC),
+    country("NG", "Nigeria", AF),
+    country("NI", "Nicaragua", NA),
+    country("NL", "Netherlands", EU),
+    country("NO", "Norway", EU),
+    country("NP", "Nepal", AS),
+    country("NR", "Nauru", OC),
+    country("NU", "Niue", OC),
+    country("NZ", "New Zealand", OC),
+    country("OM", "Oman", AS),
+    country("PA", "Panama", NA),
+    country("PE", "Peru", SA),
+    country("PF", "French Polynesia", OC),
+    country("PG", "Papua New Guinea", OC),
+    country("PH", "Philippines", AS),
+    country("PK", "Pakistan", AS),
+    country("PL", "Poland", EU),
+    country("PM", "Saint Pierre and Miquelon", NA),
+    country("PN", "Pitcairn", OC),
+    country("PR", "Puerto Rico", NA),
+    country("PS", "Palestine", AS),
+    country("PT", "Portugal", EU),
+    country("PW", "Palau", OC),
+    country("PY", "Paraguay", SA),
+    country("QA", "Qatar", AS),
+    country("RE", "Réunion", AF),
+    country("RO", "Romania", EU),
+    country("RS", "Serbia", EU),
+    country("RU", "Russia", EU),
+    country("RW", "Rwanda", AF),
+    country("SA", "Saudi Arabia", AS),
+    country("SB", "Solomon Islands", OC),
+    country("SC", "Seychelles", AF),
+    country("SD", "Sudan", AF),
+    country("SE", "Sweden", EU),
+    country("SG", "Singapore", AS),
+    country("SH", "Saint Helena, Ascension and Tristan da Cunha", AF),
+    country("SI", "Slovenia", EU),
+    country("SJ", "Svalbard and Jan Mayen", EU),
+    country("SK", "Slovakia", EU),
+    country("SL", "Sierra Leone", AF),
+    country("SM", "San Marino", EU),
+    country("SN", "Senegal", AF),
+    country("SO", "Somalia", AF),
+    country("SR", "Suriname", SA),
+    country("SS", "South Sudan", AF),
+    country("ST", "Sao Tome and Principe", AF),
+    country("SV", "El Salvador", NA),
+    country("SX", "Sint Maarten", NA),
+    country("SY", "Syria", AS),
+    country("SZ", "Eswatini", AF),
+    country("TC", "Turks and Caicos Islands", NA),
+    country("TD", "Chad", AF),
+    country("TF", "French Southern Territories", AN),
+    country("TG", "Togo", AF),
+    country("TH", "Thailand", AS),
+    country("TJ", "Tajikistan", AS),
+    country("TK", "Tokelau", OC),
+    country("TL", "Timor-Leste", AS),
+    country("TM", "Turkmenistan", AS),
+    country("TN", "Tunisia", AF),
+    country("TO", "Tonga", OC),
+    country("TR", "Türkiye", AS),
+    country("TT", "Trinidad and Tobago", NA),
+    country("TV", "Tuvalu", OC),
+    country("TW", "Taiwan", AS),
+    country("TZ", "Tanzania", AF),
+    country("UA", "Ukraine", EU),
+    country("UG", "Uganda", AF),
+    country("UM", "United States Minor Outlying Islands", OC),
+    country("US", "United States", NA),
+    country("UY", "Uruguay", SA),
+    country("UZ", "Uzbekistan", AS),
+    country("VA", "Holy See", EU),
+    country("VC", "Saint Vincent and the Grenadines", NA),
+    country("VE", "Venezuela", SA),
+    country("VG", "British Virgin Islands", NA),
+    country("VI", "U.S. Virgin Islands", NA),
+    country("VN", "Viet Nam", AS),
+    country("VU", "Vanuatu", OC),
+    country("WF", "Wallis and Futuna", OC),
+    country("WS", "Samoa", OC),
+    country("XK", "Kosovo", EU),
+    country("YE", "Yemen", AS),
+    country("YT", "Mayotte", AF),
+    country("ZA", "South Africa", AF),
+    country("ZM", "Zambia", AF),
+    country("ZW", "Zimbabwe", AF),
+];
+
+/// Alternative spellings seen in node data, by country code.
+static ALIASES: &[(&str, &str)] = &[
+    ("AE", "UAE"),
+    ("AT", "Österreich"),
+    ("AX", "Åland"),
+    ("BE", "Belgique"),
+    ("BE", "België"),
+    ("BN", "Brunei"),
+    ("BO", "Bolivia, Plurinational State of"),
+    ("BY", "Belarus, Republic of"),
+    ("CD", "DR Congo"),
+    ("CD", "Democratic Republic of the Congo"),
+    ("CD", "Congo (Kinshasa)"),
+    ("CG", "Republic of the Congo"),
+    ("CG", "Congo (Brazzaville)"),
+    ("CH", "Schweiz"),
+    ("CH", "Suisse"),
+    ("CI", "Ivory Coast"),
+    ("CV", "Cape Verde"),
+    ("CZ", "Czech Republic"),
+    ("CZ", "Česko"),
+    ("DE", "Deutschland"),
+    ("DK", "Danmark"),
+    ("ES", "España"),
+    ("FI", "Suomi"),
+    ("FK", "Falkland Islands (Malvinas)"),
+    ("FM", "Micronesia, Federated States of"),
+    ("GB", "UK"),
+    ("GB", "Great Britain"),
+    ("GB", "England"),
+    ("GB", "Scotland"),
+    ("GB", "Wales"),
+    ("GB", "Northern Ireland"),
+    ("GB", "United Kingdom of Great Britain and Northern Ireland"),
+    ("HK", "Hong Kong SAR"),
+    ("HU", "Magyarország"),
+    ("IR", "Iran, Islamic Republic of"),
+    ("IT", "Italia"),
+    ("KP", "Korea, Democratic People's Republic of"),
+    ("KR", "Korea"),
+    ("KR", "Korea, Republic of"),
+    ("KR", "Republic of Korea"),
+    ("LA", "Lao People's Democratic Republic"),
+    ("MD", "Moldova, Republic of"),
+    ("MK", "Macedonia"),
+    ("MK", "Republic of North Macedonia"),
+    ("MM", "Burma"),
+    ("MO", "Macau"),
+    ("NL", "The Netherlands"),
+    ("NL", "Holland"),
+    ("NL", "Nederland"),
+    ("NL", "Netherlands (Kingdom of the)"),
+    ("NO", "Norge"),
+    ("PL", "Polska"),
+    ("PS", "Palestine, State of"),
+    ("PS", "Palestinian Territory"),
+    ("RO", "România"),
+    ("RU", "Russian Federation"),
+    ("SE", "Sverige"),
+    ("SY", "Syrian Arab Republic"),
+    ("SZ", "Swaziland"),
+    ("TL", "East Timor"),
+    ("TR", "Turkey"),
+    ("TW", "Taiwan, Province of China"),
+    ("TZ", "Tanzania, United Republic of"),
+    ("US", "United States of America"),
+    ("US", "USA"),
+    ("US", "America"),
+    ("VA", "Vatican"),
+    ("VA", "Vatican City"),
+    ("VE", "Venezuela, Bolivarian Republic of"),
+    ("VG", "Virgin Islands, British"),
+    ("VI", "Virgin Islands, U.S."),
+    ("VN", "Vietnam"),
+];
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn code(name: &str) -> Option<&'static str> {
+        Country::find(name).map(Country::code)
+    }
+
+    #[test]
+    fn table_has_every_country_once() {
+        assert_eq!(COUNTRIES.len(), 250);
+        let codes = COUNTRIES.iter().map(|c| c.code).collect::<HashSet<_>>();
+        assert_eq!(codes.len(), COUNTRIES.len());
+        let names = COUNTRIES
+            .iter()
+            .map(|c| normalize(c.name))
+            .collect::<HashSet<_>>();
+        assert_eq!(names.len(), COUNTRIES.len());
+    }
+
+    #[test]
+    fn aliases_point_to_known_countries_without_shadowing_names() {
+        for (alias_code, alias) in ALIASES {
+            assert_eq!(code(alias), Some(*alias_code), "alias {alias}");
+        }
+        for country in COUNTRIES {
+            assert_eq!(code(country.name), Some(country.code), "{}", country.name);
+        }
+    }
+
+    #[test]
+    fn continents_parse_with_or_without_underscores() {
+        for name in ["north_america", "NorthAmerica", "northamerica"] {
+            assert_eq!(name.parse(), Ok(Continent::NorthAmerica), "{name}");
+        }
+        assert!("north america".parse::<Continent>().is_err());
+    }
+
+    #[test]
+    fn finds_codes_names_and_spellings() {
+        assert_eq!(code("be"), Some("BE"));
+        assert_eq!(code(" XK "), Some("XK"));
+        assert_eq!(code("Kosovo"), Some("XK"));
+        assert_eq!(code("Aland"), Some("AX"));
+        assert_eq!(code("Aland Islands"), Some("AX"));
+        assert_eq!(code("the netherlands"), Some("NL"));
+        assert_eq!(code("Côte d'Ivoire"), Some("CI"));
+        assert_eq!(code("UNITED  STATES"), Some("US"));
+        assert_eq!(code("Atlantis"), None);
+        assert_eq!(code(""), None);
+    }
+}
@@ -0,0 +1,443 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::{Node, Resources};
+
+/// Signed difference in resources.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ResourceDelta {
+    #[serde(rename = "CRU delta")]
+    pub cru: i64,
+    #[serde(rename = "MRU delta")]
+    pub mru: i64,
+    #[serde(rename = "SRU delta")]
+    pub sru: i64,
+    #[serde(rename = "HRU delta")]
+    pub hru: i64,
+}
+
+impl ResourceDelta {
+    /// The difference going from `old` to `new`.
+    pub fn between(old: &Resources, new: &Resources) -> Self {
+        Self {
+            cru: new.cru() as i64 - old.cru() as i64,
+            mru: new.mru() as i64 - old.mru() as i64,
+            sru: new.sru() as i64 - old.sru() as i64,
+            hru: new.hru() as i64 - old.hru() as i64,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl std::ops::Neg for ResourceDelta {
+    type Output = ResourceDelta;
+
+    fn neg(self) -> ResourceDelta {
+        ResourceDelta {
+            cru: -self.cru,
+            mru: -self.mru,
+            sru: -self.sru,
+            hru: -self.hru,
+        }
+    }
+}
+
+impl std::ops::AddAssign for ResourceDelta {
+    fn add_assign(&mut self, rhs: ResourceDelta) {
+        self.cru += rhs.cru;
+        self.mru += rhs.mru;
+        self.sru += rhs.sru;
+        self.hru += rhs.hru;
+    }
+}
+
+/// The kind of change of a single node.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// The node is only present in the new set.
+    #[default]
+    Added,
+    /// The node is only present in the old set.
+    Removed,
+    /// The node moved to a different farm.
+    FarmChanged,
+    /// No resource decreased, and at least 1 increased.
+    Upgraded,
+    /// No resource increased, and at least 1 decreased.
+    Downgraded,
+    /// Some resources increased while others decreased.
+    ResourcesChanged,
+}
+
+/// A single change of a node. A node which changed both farm and resources has 2 changes.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct NodeChange {
+    change: ChangeKind,
+    #[serde(rename = "node id")]
+    node_id: u32,
+    #[serde(rename = "farm id")]
+    farm_id: u32,
+    #[serde(rename = "old farm id")]
+    old_farm_id: Option<u32>,
+    #[serde(flatten)]
+    delta: ResourceDelta,
+}
+
+impl NodeChange {
+    /// The kind of change.
+    pub fn change(&self) -> ChangeKind {
+        self.change
+    }
+
+    /// Id of the node.
+    pub fn node_id(&self) -> u32 {
+        self.node_id
+    }
+
+    /// Farm of the node. For removed nodes this is the farm in the old set, otherwise the farm
+    /// in the new set.
+    pub fn farm_id(&self) -> u32 {
+        self.farm_id
+    }
+
+    /// Farm of the node in the old set, only set for farm changes.
+    pub fn old_farm_id(&self) -> Option<u32> {
+        self.old_farm_id
+    }
+
+    /// Change in resources. For added and removed nodes this is all their resources.
+    pub fn delta(&self) -> &ResourceDelta {
+        &self.delta
+    }
+}
+
+/// Changes of all nodes of a single farm.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct FarmDiff {
+    #[serde(rename = "farm id")]
+    farm_id: u32,
+    #[serde(rename = "nodes added")]
+    added: u64,
+    #[serde(rename = "nodes removed")]
+    removed: u64,
+    #[serde(rename = "nodes moved in")]
+    moved_in: u64,
+    #[serde(rename = "nodes moved out")]
+    moved_out: u64,
+    #[serde(rename = "nodes upgraded")]
+    upgraded: u64,
+    #[serde(rename = "nodes downgraded")]
+    downgraded: u64,
+    #[serde(rename = "node delta")]
+    node_delta: i64,
+    #[serde(flatten)]
+    delta: ResourceDelta,
+}
+
+impl FarmDiff {
+    /// Id of the farm.
+    pub fn farm_id(&self) -> u32 {
+        self.farm_id
+    }
+
+    /// Net change in the amount of nodes of the farm.
+    pub fn node_delta(&self) -> i64 {
+        self.node_delta
+    }
+
+    /// Net change in resources of the farm.
+    pub fn delta(&self) -> &ResourceDelta {
+        &self.delta
+    }
+}
+
+/// Totals of all changes.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct DiffSummary {
+    #[serde(rename = "old nodes")]
+    old_nodes: u64,
+    #[serde(rename = "new nodes")]
+    new_nodes: u64,
+    #[serde(rename = "nodes added")]
+    added: u64,
+    #[serde(rename = "nodes removed")]
+    removed: u64,
+    #[serde(rename = "farm changes")]
+    farm_changes: u64,
+    #[serde(rename = "upgrades")]
+    upgrades: u64,
+    #[serde(rename = "downgrades")]
+    downgrades: u64,
+    #[serde(rename = "mixed resource changes")]
+    mixed: u64,
+    #[serde(flatten)]
+    delta: ResourceDelta,
+}
+
+impl DiffSummary {
+    /// Amount of nodes added.
+    pub fn added(&self) -> u64 {
+        self.added
+    }
+
+    /// Amount of nodes removed.
+    pub fn removed(&self) -> u64 {
+        self.removed
+    }
+
+    /// Net change in resources.
+    pub fn delta(&self) -> &ResourceDelta {
+        &self.delta
+    }
+}
+
+/// All changes between two sets of nodes.
+#[derive(Debug, Clone)]
+pub struct NodeDiff {
+    old_nodes: u64,
+    new_nodes: u64,
+    changes: Vec<NodeChange>,
+}
+
+impl NodeDiff {
+    /// All changes, ordered by node id.
+    pub fn changes(&self) -> &[NodeChange] {
+        &self.changes
+    }
+
+    /// Totals of all changes.
+    pub fn summary(&self) -> DiffSummary {
+        let mut summary = DiffSummary {
+            old_nodes: self.old_nodes,
+            new_nodes: self.new_nodes,
+            ..Default::default()
+        };
+        for change in &self.changes {
+            match change.change {
+                ChangeKind::Added => summary.added += 1,
+                ChangeKind::Removed => summary.removed += 1,
+                ChangeKind::FarmChanged => summary.farm_changes += 1,
+                ChangeKind::Upgraded => summary.upgrades += 1,
+                ChangeKind::Downgraded => summary.downgrades += 1,
+                ChangeKind::ResourcesChanged => summary.mixed += 1,
+            }
+            // Moving a node between farms doesn't change the total resources.
+            if change.change != ChangeKind::FarmChanged {
+                summary.delta += change.delta;
+            }
+        }
+        summary
+    }
+
+    /// Changes per farm, ordered by farm id. Farms without changes are omitted.
+    pub fn by_farm(&self) -> Vec<FarmDiff> {
+        fn farm(farms: &mut BTreeMap<u32, FarmDiff>, farm_id: u32) -> &mut FarmDiff {
+            farms.entry(farm_id).or_insert_with(|| FarmDiff {
+                farm_id,
+                ..Default::default()
+            })
+        }
+
+        let mut farms = BTreeMap::new();
+        for change in &self.changes {
+            let current = farm(&mut farms, change.farm_id);
+            current.delta += change.delta;
+            match change.change {
+                ChangeKind::Added => {
+                    current.added += 1;
+                    current.node_delta += 1;
+                }
+                ChangeKind::Removed => {
+                    current.removed += 1;
+                    current.node_delta -= 1;
+                }
+                ChangeKind::FarmChanged => {
+                    current.moved_in += 1;
+                    current.node_delta += 1;
+                }
+                ChangeKind::Upgraded => current.upgraded += 1,
+                ChangeKind::Downgraded => current.downgraded += 1,
+                ChangeKind::ResourcesChanged => {}
+            }
+
+            if let Some(old_farm_id) = change.old_farm_id {
+                let old = farm(&mut farms, old_farm_id);
+                old.moved_out += 1;
+                old.node_delta -= 1;
+                old.delta += -change.delta;
+            }
+        }
+
+        farms.into_values().collect()
+    }
+}
+
+/// Compare 2 sets of nodes by node id.
+pub fn diff(old: &[Node], new: &[Node]) -> NodeDiff {
+    let zero = Resources::default();
+    let old_by_id = old
+        .iter()
+        .map(|node| (node.node_id(), node))
+        .collect::<HashMap<_, _>>();
+    let new_by_id = new
+        .iter()
+        .map(|node| (node.node_id(), node))
+        .collect::<HashMap<_, _>>();
+
+    let mut changes = Vec::new();
+    for node in new {
+        let Some(old_node) = old_by_id.get(&node.node_id()) else {
+            changes.push(NodeChange {
+                change: ChangeKind::Added,
+                node_id: node.node_id(),
+                farm_id: node.farm_id(),
+                old_farm_id: None,
+                delta: ResourceDelta::between(&zero, node.resources_total()),
+            });
+            continue;
+        };
+
+        let delta = ResourceDelta::between(old_node.resources_total(), node.resources_total());
+        if old_node.farm_id() != node.farm_id() {
+            // The node carries its old resources to the new farm, any resource change is
+            // reported separately.
+            changes.push(NodeChange {
+                change: ChangeKind::FarmChanged,
+                node_id: node.node_id(),
+                farm_id: node.farm_id(),
+                old_farm_id: Some(old_node.farm_id()),
+                delta: ResourceDelta::between(&zero, old_node.resources_total()),
+            });
+        }
+        if !delta.is_zero() {
+            let deltas = [delta.cru, delta.mru, delta.sru, delta.hru];
+            let change = if deltas.iter().all(|d| *d >= 0) {
+                ChangeKind::Upgraded
+            } else if deltas.iter().all(|d| *d <= 0) {
+                ChangeKind::Downgraded
+            } else {
+                ChangeKind::ResourcesChanged
+            };
+            changes.push(NodeChange {
+                change,
+                node_id: node.node_id(),
+                farm_id: node.farm_id(),
+                old_farm_id: None,
+                delta,
+            });
+        }
+    }
+    for node in old {
+        if !new_by_id.contains_key(&node.node_id()) {
+            changes.push(NodeChange {
+                change: ChangeKind::Removed,
+                node_id: node.node_id(),
+                farm_id: node.farm_id(),
+                old_farm_id: None,
+                delta: ResourceDelta::between(node.resources_total(), &zero),
+            });
+        }
+    }
+    changes.sort_by_key(|change| change.node_id);
+
+    NodeDiff {
+        old_nodes: old.len() as u64,
+        new_nodes: new.len() as u64,
+        changes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(node_id: u32, farm_id: u32, cru: u64, sru: u64) -> Node {
+        Node::new(node_id, farm_id, 0, Resources::new(cru, 0, sru, 0))
+    }
+
+    fn kinds(diff: &NodeDiff) -> Vec<(u32, ChangeKind)> {
+        diff.changes()
+            .iter()
+            .map(|c| (c.node_id(), c.change()))
+            .collect()
+    }
+
+    #[test]
+    fn classifies_node_changes() {
+        let old = [
+            node(1, 1, 4, 100),
+            node(2, 1, 4, 100),
+            node(3, 1, 4, 100),
+            node(4, 1, 4, 100),
+            node(5, 1, 4, 100),
+            node(6, 1, 4, 100),
+        ];
+        let new = [
+            node(1, 1, 4, 100),
+            node(2, 1, 8, 100),
+            node(3, 1, 4, 50),
+            node(4, 1, 8, 50),
+            node(5, 2, 4, 100),
+            node(7, 2, 2, 10),
+        ];
+        let diff = diff(&old, &new);
+
+        assert_eq!(
+            kinds(&diff),
+            [
+                (2, ChangeKind::Upgraded),
+                (3, ChangeKind::Downgraded),
+                (4, ChangeKind::ResourcesChanged),
+                (5, ChangeKind::FarmChanged),
+                (6, ChangeKind::Removed),
+                (7, ChangeKind::Added),
+            ]
+        );
+        let changes = diff.changes();
+        assert_eq!(changes[2].delta().cru, 4);
+        assert_eq!(changes[2].delta().sru, -50);
+        assert_eq!(changes[3].old_farm_id(), Some(1));
+        assert_eq!(changes[3].farm_id(), 2);
+        assert_eq!(changes[4].delta().cru, -4);
+        assert_eq!(changes[5].delta().sru, 10);
+    }
+
+    #[test]
+    fn farm_and_resource_change_are_reported_separately() {
+        let diff = diff(&[node(1, 1, 4, 100)], &[node(1, 2, 8, 100)]);
+
+        assert_eq!(
+            kinds(&diff),
+            [(1, ChangeKind::FarmChanged), (1, ChangeKind::Upgraded)]
+        );
+        // The move carries the old resources, the upgrade only the difference.
+        assert_eq!(diff.changes()[0].delta().cru, 4);
+        assert_eq!(diff.changes()[1].delta().cru, 4);
+
+        let summary = diff.summary();
+        assert_eq!((summary.added(), summary.removed()), (0, 0));
+        assert_eq!(summary.delta().cru, 4);
+
+        let farms = diff.by_farm();
+        assert_eq!(farms.len(), 2);
+        assert_eq!((farms[0].farm_id(), farms[0].node_delta()), (1, -1));
+        assert_eq!(farms[0].delta().cru, -4);
+        assert_eq!((farms[1].farm_id(), farms[1].node_delta()), (2, 1));
+        assert_eq!(farms[1].delta().cru, 8);
+    }
+
+    #[test]
+    fn identical_sets_have_no_changes() {
+        let nodes = [node(1, 1, 4, 100), node(2, 3, 8, 0)];
+        let diff = diff(&nodes, &nodes);
+
+        assert!(diff.changes().is_empty());
+        assert!(diff.by_farm().is_empty());
+        assert_eq!(*diff.summary().delta(), ResourceDelta::default());
+    }
+}
@@ -0,0 +1,177 @@
+use std::{fmt, io, path::PathBuf, time::Duration};
+
+use reqwest::StatusCode;
+
+use crate::{types::GraphQLError, Format, Tolerance};
+
+/// Errors which can happen while fetching, aggregating or writing nodes.
+#[derive(Debug)]
+pub enum NodeCounterError {
+    /// The HTTP client could not be constructed.
+    Client { source: reqwest::Error },
+    /// A request to the endpoint could not be sent, or its response could not be read.
+    Network {
+        endpoint: String,
+        source: reqwest::Error,
+    },
+    /// The endpoint replied with a non-success HTTP status.
+    HttpStatus {
+        endpoint: String,
+        status: StatusCode,
+        body: String,
+        /// Delay requested by the server in a `Retry-After` header.
+        retry_after: Option<Duration>,
+    },
+    /// The graphql server returned errors. If `partial` is set, some data was returned as well.
+    GraphQL {
+        errors: Vec<GraphQLError>,
+        partial: bool,
+    },
+    /// The graphql server returned neither data nor errors.
+    MissingData,
+    /// Fewer nodes were fetched than the server reported to have.
+    IncompleteFetch { expected: u64, fetched: u64 },
+    /// A response body could not be decoded.
+    Decode {
+        endpoint: String,
+        source: serde_json::Error,
+    },
+    /// Reading or writing a file failed.
+    Io { path: PathBuf, source: io::Error },
+    /// The snapshot store could not be read or written.
+    Store {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+    /// The requested snapshot does not exist in the store.
+    SnapshotNotFound { path: PathBuf, snapshot: String },
+    /// More nodes differ between the graphql indexer and the Grid Proxy than tolerated.
+    VerifyFailed {
+        mismatched: u64,
+        tolerance: Tolerance,
+    },
+    /// A command line argument needed by the command was not given.
+    MissingArgument {
+        argument: &'static str,
+        required_by: &'static str,
+    },
+    /// The output format can't be written by the command.
+    UnsupportedFormat {
+        format: Format,
+        command: &'static str,
+    },
+}
+
+impl NodeCounterError {
+    /// The process exit code to use when failing with this error. Codes start at 3: 1 is the
+    /// conventional generic failure code and 2 is used for command line usage errors. Panics exit
+    /// with 101.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            NodeCounterError::Client { .. } => 3,
+            NodeCounterError::Network { .. } => 4,
+            NodeCounterError::HttpStatus { .. } => 5,
+            NodeCounterError::GraphQL { partial: false, .. } => 6,
+            NodeCounterError::GraphQL { partial: true, .. } => 7,
+            NodeCounterError::MissingData => 8,
+            NodeCounterError::IncompleteFetch { .. } => 9,
+            NodeCounterError::Decode { .. } => 10,
+            NodeCounterError::Io { .. } => 11,
+            NodeCounterError::Store { .. } => 12,
+            NodeCounterError::SnapshotNotFound { .. } => 13,
+            NodeCounterError::VerifyFailed { .. } => 14,
+            NodeCounterError::MissingArgument { .. } => 15,
+            NodeCounterError::UnsupportedFormat { .. } => 16,
+        }
+    }
+}
+
+impl fmt::Display for NodeCounterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeCounterError::Client { source } => {
+                write!(f, "could not create http client: {source}")
+            }
+            NodeCounterError::Network { endpoint, source } => {
+                write!(f, "request to {endpoint} failed: {source}")
+            }
+            NodeCounterError::HttpStatus {
+                endpoint,
+                status,
+                body,
+                ..
+            } => {
+                write!(f, "{endpoint} replied with status {status}")?;
+                if !body.is_empty() {
+                    write!(f, ": {body}")?;
+                }
+                Ok(())
+            }
+            NodeCounterError::GraphQL { errors, partial } => {
+                if *partial {
+                    f.write_str("graphql server returned partial data with errors:")?;
+                } else {
+                    f.write_str("graphql server returned errors:")?;
+                }
+                for error in errors {
+                    write!(f, "\n  - {error}")?;
+                }
+                Ok(())
+            }
+            NodeCounterError::MissingData => f.write_str("graphql server returned no data"),
+            NodeCounterError::IncompleteFetch { expected, fetched } => write!(
+                f,
+                "fetched {fetched} nodes but server reports {expected} nodes"
+            ),
+            NodeCounterError::Decode { endpoint, source } => {
+                write!(f, "could not decode response from {endpoint}: {source}")
+            }
+            NodeCounterError::Io { path, source } => {
+                write!(f, "i/o error on {}: {source}", path.display())
+            }
+            NodeCounterError::Store { path, source } => {
+                write!(f, "snapshot store {} failed: {source}", path.display())
+            }
+            NodeCounterError::SnapshotNotFound { path, snapshot } => write!(
+                f,
+                "snapshot {snapshot} not found in store {}",
+                path.display()
+            ),
+            NodeCounterError::VerifyFailed {
+                mismatched,
+                tolerance,
+            } => write!(
+                f,
+                "{mismatched} nodes differ between graphql and grid proxy, more than the tolerance of {tolerance}"
+            ),
+            NodeCounterError::MissingArgument {
+                argument,
+                required_by,
+            } => write!(f, "{required_by} requires {argument}"),
+            NodeCounterError::UnsupportedFormat { format, command } => {
+                write!(f, "{format} output is not supported by {command}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NodeCounterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NodeCounterError::Client { source } | NodeCounterError::Network { source, .. } => {
+                Some(source)
+            }
+            NodeCounterError::Decode { source, .. } => Some(source),
+            NodeCounterError::Io { source, .. } => Some(source),
+            NodeCounterError::Store { source, .. } => Some(source),
+            NodeCounterError::HttpStatus { .. }
+            | NodeCounterError::SnapshotNotFound { .. }
+            | NodeCounterError::GraphQL { .. }
+            | NodeCounterError::MissingData
+            | NodeCounterError::IncompleteFetch { .. }
+            | NodeCounterError::VerifyFailed { .. }
+            | NodeCounterError::MissingArgument { .. }
+            | NodeCounterError::UnsupportedFormat { .. } => None,
+        }
+    }
+}
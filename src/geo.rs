@@ -0,0 +1,171 @@
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::{Location, Node};
+
+/// A geographic area to restrict nodes to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Area {
+    /// All locations within a great-circle distance of a center.
+    Radius { center: Location, radius_km: f64 },
+    /// All locations inside a bounding box.
+    BoundingBox(BoundingBox),
+}
+
+impl Area {
+    /// Whether the location lies in the area.
+    pub fn contains(&self, location: &Location) -> bool {
+        match self {
+            Area::Radius { center, radius_km } => center.distance_km(location) <= *radius_km,
+            Area::BoundingBox(bbox) => bbox.contains(location),
+        }
+    }
+
+    /// Whether the node lies in the area. Nodes without a location never do.
+    pub fn contains_node(&self, node: &Node) -> bool {
+        node.location()
+            .is_some_and(|location| self.contains(&location))
+    }
+}
+
+/// A box bounded by 2 latitudes and 2 longitudes, in degrees. If `west` is larger than `east`,
+/// the box crosses the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BoundingBox {
+    south: f64,
+    west: f64,
+    north: f64,
+    east: f64,
+}
+
+impl BoundingBox {
+    /// Create a new `BoundingBox`. Returns `None` if a coordinate is out of range, or `south` is
+    /// larger than `north`.
+    pub fn new(south: f64, west: f64, north: f64, east: f64) -> Option<Self> {
+        let valid = Location::new(south, west).is_some()
+            && Location::new(north, east).is_some()
+            && south <= north;
+        valid.then_some(Self {
+            south,
+            west,
+            north,
+            east,
+        })
+    }
+
+    /// Whether the location lies in the box, including its edges.
+    pub fn contains(&self, location: &Location) -> bool {
+        let longitude = location.longitude();
+        let in_longitude = if self.west <= self.east {
+            (self.west..=self.east).contains(&longitude)
+        } else {
+            longitude >= self.west || longitude <= self.east
+        };
+        (self.south..=self.north).contains(&location.latitude()) && in_longitude
+    }
+}
+
+impl FromStr for BoundingBox {
+    type Err = String;
+
+    /// Parse `<south>,<west>,<north>,<east>` in degrees.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || format!("invalid bounding box \"{s}\", expected <south>,<west>,<north>,<east>");
+        let coordinates = s
+            .split(',')
+            .map(|c| c.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        match coordinates[..] {
+            [south, west, north, east] => {
+                BoundingBox::new(south, west, north, east).ok_or_else(invalid)
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resources;
+
+    fn at(latitude: f64, longitude: f64) -> Location {
+        Location::new(latitude, longitude).unwrap()
+    }
+
+    #[test]
+    fn bounding_box_contains_its_edges() {
+        let bbox = BoundingBox::new(40., -10., 60., 20.).unwrap();
+        assert!(bbox.contains(&at(50., 4.)));
+        assert!(bbox.contains(&at(40., -10.)));
+        assert!(bbox.contains(&at(60., 20.)));
+        assert!(!bbox.contains(&at(39.9, 4.)));
+        assert!(!bbox.contains(&at(50., 20.1)));
+    }
+
+    #[test]
+    fn bounding_box_crosses_antimeridian() {
+        let bbox: BoundingBox = "-20,170,20,-170".parse().unwrap();
+        for longitude in [170., 178., 180., -180., -175., -170.] {
+            assert!(bbox.contains(&at(0., longitude)), "{longitude}");
+        }
+        for longitude in [169.9, 0., -169.9] {
+            assert!(!bbox.contains(&at(0., longitude)), "{longitude}");
+        }
+        assert!(!bbox.contains(&at(21., 180.)));
+    }
+
+    #[test]
+    fn bounding_box_rejects_invalid_coordinates() {
+        assert!(BoundingBox::new(10., 0., -10., 0.).is_none());
+        assert!(BoundingBox::new(-91., 0., 0., 0.).is_none());
+        assert!(BoundingBox::new(0., -181., 0., 0.).is_none());
+        assert!("1,2,3".parse::<BoundingBox>().is_err());
+        assert!("1,2,3,x".parse::<BoundingBox>().is_err());
+        assert_eq!(
+            " -1, 2 ,3,4".parse::<BoundingBox>(),
+            Ok(BoundingBox::new(-1., 2., 3., 4.).unwrap())
+        );
+    }
+
+    #[test]
+    fn haversine_distance() {
+        let brussels = at(50.8503, 4.3517);
+        let paris = at(48.8566, 2.3522);
+        assert!((brussels.distance_km(&paris) - 264.).abs() < 1.);
+        assert_eq!(brussels.distance_km(&brussels), 0.);
+        // 1 degree of longitude on the equator, across the antimeridian.
+        assert!((at(0., 179.5).distance_km(&at(0., -179.5)) - 111.2).abs() < 0.1);
+        // Half the circumference for antipodal points.
+        assert!((at(0., 0.).distance_km(&at(0., 180.)) - 20015.1).abs() < 0.1);
+    }
+
+    #[test]
+    fn radius_contains_locations_within_distance() {
+        let area = Area::Radius {
+            center: at(50.8503, 4.3517),
+            radius_km: 265.,
+        };
+        assert!(area.contains(&at(48.8566, 2.3522)));
+        assert!(!area.contains(&at(52.52, 13.405)));
+
+        let area = Area::Radius {
+            center: at(50.8503, 4.3517),
+            radius_km: 263.,
+        };
+        assert!(!area.contains(&at(48.8566, 2.3522)));
+    }
+
+    #[test]
+    fn nodes_without_location_are_outside() {
+        let node = Node::new(1, 1, 0, Resources::new(1, 0, 0, 0));
+        let area = Area::BoundingBox(BoundingBox::new(-90., -180., 90., 180.).unwrap());
+        assert!(!area.contains_node(&node));
+        let node = node.with_location(None, None, Some(at(0., 0.)));
+        assert!(area.contains_node(&node));
+    }
+}
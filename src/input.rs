@@ -0,0 +1,47 @@
+use std::{io::Read, path::Path};
+
+use serde::{de::IgnoredAny, Deserialize};
+
+use crate::{raw, Node, NodeCounterError, NodeRow, RawFetch};
+
+/// Tells a raw fetch, which holds the responses, apart from a JSON document. The raw response
+/// bodies can't be buffered by an untagged enum, so the file is probed first.
+#[derive(Deserialize)]
+struct Probe {
+    #[serde(default)]
+    responses: Option<IgnoredAny>,
+}
+
+/// A JSON document as written by the JSON format. Metadata is ignored.
+#[derive(Deserialize)]
+struct Document {
+    rows: Vec<NodeRow>,
+}
+
+/// Read nodes from a file. This can be either a raw fetch, as saved by [`RawFetch::save`], or a
+/// JSON file as written by [`write_nodes`](crate::write_nodes) with the JSON format. Gzip
+/// compressed files are detected automatically.
+pub fn read_nodes(path: &Path) -> Result<Vec<Node>, NodeCounterError> {
+    let mut json = String::new();
+    raw::open(path)?
+        .read_to_string(&mut json)
+        .map_err(|source| NodeCounterError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    let decode_err = |source| NodeCounterError::Decode {
+        endpoint: path.display().to_string(),
+        source,
+    };
+
+    if serde_json::from_str::<Probe>(&json)
+        .map_err(decode_err)?
+        .responses
+        .is_some()
+    {
+        let raw: RawFetch = serde_json::from_str(&json).map_err(decode_err)?;
+        return Ok(raw.into_nodes());
+    }
+    let document: Document = serde_json::from_str(&json).map_err(decode_err)?;
+    Ok(document.rows.into_iter().map(Node::from).collect())
+}
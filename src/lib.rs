@@ -0,0 +1,352 @@
+//! Count the amount of nodes registered on the threefold grid over time.
+
+use std::{sync::atomic::AtomicUsize, time::Duration};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+#[macro_use]
+mod macros;
+
+mod aggregate;
+mod certification;
+mod country;
+mod diff;
+mod error;
+mod geo;
+mod input;
+mod network;
+mod output;
+mod raw;
+mod retry;
+mod source;
+mod status;
+mod store;
+mod types;
+mod verify;
+
+pub use aggregate::{
+    aggregate, aggregate_by_farm, aggregate_by_region, largest_farms, FarmAggregate, Granularity,
+    NodeTotals, Period, PeriodAggregate, Region, RegionAggregate,
+};
+pub use certification::{FarmCertification, NodeCertification};
+pub use country::{Continent, Country};
+pub use diff::{diff, ChangeKind, DiffSummary, FarmDiff, NodeChange, NodeDiff, ResourceDelta};
+pub use error::NodeCounterError;
+pub use geo::{Area, BoundingBox};
+pub use input::read_nodes;
+pub use network::Network;
+pub use output::{
+    write_aggregates, write_farm_aggregates, write_nodes, write_region_aggregates, write_rows,
+    AggregateRow, Columns, FarmRow, Format, Layout, Metadata, NodeRow, Query, RegionRow, RowWriter,
+};
+pub use raw::RawFetch;
+use raw::Recorder;
+pub use retry::RetryPolicy;
+use source::{GraphQLSource, GridProxySource, Http};
+pub use source::{NodeSource, Source};
+pub use status::NodeStatus;
+pub use store::{Snapshot, SnapshotStore};
+pub use types::{
+    de_u64, GraphQLError, GraphQLErrorLocation, GraphQLRequest, GraphQLResponse, Location, Node,
+    NodeCountReply, NodeReply, PageVariables, Resources,
+};
+pub use verify::{verify, Discrepancy, DiscrepancyKind, Tolerance, Verification, VerifySummary};
+
+const USER_AGENT: &str = "node_counter_agent";
+
+/// Default amount of nodes requested per page.
+pub const DEFAULT_PAGE_SIZE: u32 = 1000;
+
+/// Default time after the last report of a node before it is considered down.
+pub const DEFAULT_STALENESS: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Fetches nodes from a graphql or Grid Proxy endpoint and aggregates them over time.
+pub struct NodeCounter {
+    network: Network,
+    source: Source,
+    endpoint: Option<String>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    granularity: Granularity,
+    area: Option<Area>,
+    staleness: Duration,
+    activity: bool,
+    page_size: u32,
+    retry: RetryPolicy,
+    fallback_endpoints: Vec<String>,
+    /// Index of the endpoint currently in use, see [`Http`].
+    active_endpoint: AtomicUsize,
+    client: reqwest::Client,
+}
+
+impl NodeCounter {
+    /// Create a new `NodeCounter` for mainnet, counting monthly from the creation of the first
+    /// node until now.
+    pub fn new() -> Result<Self, NodeCounterError> {
+        let client = reqwest::ClientBuilder::new()
+            .user_agent(USER_AGENT)
+            .gzip(true)
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|source| NodeCounterError::Client { source })?;
+
+        Ok(Self {
+            network: Network::default(),
+            source: Source::default(),
+            endpoint: None,
+            from: None,
+            to: None,
+            granularity: Granularity::default(),
+            area: None,
+            staleness: DEFAULT_STALENESS,
+            activity: true,
+            page_size: DEFAULT_PAGE_SIZE,
+            retry: RetryPolicy::default(),
+            fallback_endpoints: Vec::new(),
+            active_endpoint: AtomicUsize::new(0),
+            client,
+        })
+    }
+
+    /// Set the network to query. Unless a custom endpoint is set, the endpoint of the source on
+    /// the network is used.
+    pub fn network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Set the backend to fetch nodes from.
+    pub fn source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Set a custom endpoint to query, overriding the endpoint of the network.
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// The network this counter is configured for.
+    pub fn selected_network(&self) -> Network {
+        self.network
+    }
+
+    /// The backend this counter fetches nodes from.
+    pub fn selected_source(&self) -> Source {
+        self.source
+    }
+
+    /// Set the endpoints to try, in order, when the primary endpoint keeps failing.
+    pub fn fallback_endpoints(mut self, endpoints: Vec<String>) -> Self {
+        self.fallback_endpoints = endpoints;
+        self
+    }
+
+    /// Set how failed requests are retried.
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// The endpoint which will be queried. After a failover, this is the fallback endpoint in
+    /// use.
+    pub fn selected_endpoint(&self) -> &str {
+        self.http().endpoint()
+    }
+
+    /// Requests to the primary endpoint, failing over to the fallback endpoints.
+    fn http(&self) -> Http<'_> {
+        let primary = self
+            .endpoint
+            .as_deref()
+            .unwrap_or_else(|| self.source.url(self.network));
+        let endpoints = std::iter::once(primary)
+            .chain(self.fallback_endpoints.iter().map(String::as_str))
+            .collect();
+        Http::new(&self.client, self.retry, endpoints, &self.active_endpoint)
+    }
+
+    /// Metadata describing output generated by this counter.
+    pub fn metadata(&self) -> Metadata {
+        Metadata::new(self.network, self.selected_endpoint()).with_query(Query {
+            granularity: self.granularity,
+            from: self.from,
+            to: self.to,
+            area: self.area,
+        })
+    }
+
+    /// Set the start of the time range. If not set, the creation time of the oldest node is used.
+    pub fn from(mut self, from: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Set the end of the time range. If not set, the current time is used.
+    pub fn to(mut self, to: DateTime<Utc>) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    /// Set the size of the periods to aggregate in.
+    pub fn granularity(mut self, granularity: Granularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    /// Only aggregate nodes located in the area. Nodes without a location are left out.
+    pub fn area(mut self, area: Area) -> Self {
+        self.area = Some(area);
+        self
+    }
+
+    /// Set the time after the last report of a node before it is counted as down.
+    pub fn staleness(mut self, staleness: Duration) -> Self {
+        self.staleness = staleness;
+        self
+    }
+
+    /// Set whether aggregated nodes are split by status. Without it, the totals per status are
+    /// zero, and the status changes of the nodes aren't swept.
+    pub fn activity(mut self, activity: bool) -> Self {
+        self.activity = activity;
+        self
+    }
+
+    /// Set the amount of nodes requested per page. A page size of 0 is treated as 1.
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Fetch all nodes from the source, one page at a time.
+    ///
+    /// After all pages are fetched, the amount of nodes is checked against the total count
+    /// reported by the server. If fewer nodes were fetched, an error is returned.
+    pub async fn fetch_nodes(&self) -> Result<Vec<Node>, NodeCounterError> {
+        self.fetch(None).await
+    }
+
+    /// Fetch all nodes like [`fetch_nodes`](Self::fetch_nodes), keeping the response bodies
+    /// exactly as received, so the fetch can be saved and replayed later.
+    pub async fn fetch_raw(&self) -> Result<RawFetch, NodeCounterError> {
+        let fetched_at = Utc::now();
+        let recorder = Recorder::default();
+        let nodes = self.fetch(Some(&recorder)).await?;
+        Ok(RawFetch::new(
+            fetched_at,
+            self.network,
+            self.source,
+            self.selected_endpoint(),
+            recorder.into_responses(),
+            nodes,
+        ))
+    }
+
+    /// Fetch all nodes from the source, recording the listed pages in the recorder if any.
+    async fn fetch(&self, recorder: Option<&Recorder>) -> Result<Vec<Node>, NodeCounterError> {
+        match self.source {
+            Source::GraphQL => {
+                fetch_checked(&GraphQLSource::new(self.http(), self.page_size).recording(recorder))
+                    .await
+            }
+            Source::GridProxy => {
+                fetch_checked(
+                    &GridProxySource::new(self.http(), self.page_size).recording(recorder),
+                )
+                .await
+            }
+        }
+    }
+
+    /// Fetch the total amount of nodes known by the source.
+    pub async fn fetch_node_count(&self) -> Result<u64, NodeCounterError> {
+        match self.source {
+            Source::GraphQL => {
+                GraphQLSource::new(self.http(), self.page_size)
+                    .fetch_node_count()
+                    .await
+            }
+            Source::GridProxy => {
+                GridProxySource::new(self.http(), self.page_size)
+                    .fetch_node_count()
+                    .await
+            }
+        }
+    }
+
+    /// Keep only the nodes in the configured area, if any.
+    pub fn filter_area(&self, nodes: Vec<Node>) -> Vec<Node> {
+        match &self.area {
+            Some(area) => nodes
+                .into_iter()
+                .filter(|n| area.contains_node(n))
+                .collect(),
+            None => nodes,
+        }
+    }
+
+    /// Aggregate already fetched nodes over the configured time range.
+    pub fn aggregate(&self, nodes: &[Node]) -> Vec<PeriodAggregate> {
+        let staleness = self.activity.then_some(self.staleness);
+        aggregate(nodes, &self.periods(nodes), staleness)
+    }
+
+    /// Aggregate already fetched nodes per farm over the configured time range.
+    pub fn aggregate_by_farm(&self, nodes: &[Node]) -> Vec<FarmAggregate> {
+        aggregate_by_farm(nodes, &self.periods(nodes))
+    }
+
+    /// Aggregate already fetched nodes per country or continent over the configured time range.
+    pub fn aggregate_by_region(&self, nodes: &[Node], region: Region) -> Vec<RegionAggregate> {
+        aggregate_by_region(nodes, &self.periods(nodes), region)
+    }
+
+    /// The periods in the configured time range. Without a start, the range starts at the
+    /// oldest node.
+    pub fn periods(&self, nodes: &[Node]) -> Vec<Period> {
+        let to = self.to.unwrap_or_else(Utc::now);
+        let from = self.from.unwrap_or_else(|| {
+            nodes
+                .iter()
+                .map(Node::created)
+                .min()
+                .and_then(|created| Utc.timestamp_opt(created, 0).single())
+                .unwrap_or(to)
+        });
+        self.granularity.periods(from, to)
+    }
+
+    /// The nodes with a known location which are counted in the last period of the configured
+    /// time range, i.e. which were created before its start.
+    pub fn located_nodes(&self, nodes: &[Node]) -> Vec<Node> {
+        let Some(last) = self.periods(nodes).pop() else {
+            return Vec::new();
+        };
+        nodes
+            .iter()
+            .filter(|node| node.created() < last.start.timestamp() && node.location().is_some())
+            .cloned()
+            .collect()
+    }
+
+    /// Fetch all nodes in the configured area and aggregate them over the configured time range.
+    pub async fn count(&self) -> Result<Vec<PeriodAggregate>, NodeCounterError> {
+        let nodes = self.filter_area(self.fetch_nodes().await?);
+        Ok(self.aggregate(&nodes))
+    }
+}
+
+/// Fetch all nodes from a source, and check them against the total count reported by it.
+async fn fetch_checked(source: &impl NodeSource) -> Result<Vec<Node>, NodeCounterError> {
+    let expected = source.fetch_node_count().await?;
+    let nodes = source.fetch_nodes().await?;
+
+    let fetched = nodes.len() as u64;
+    if fetched < expected {
+        return Err(NodeCounterError::IncompleteFetch { expected, fetched });
+    }
+
+    Ok(nodes)
+}
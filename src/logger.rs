@@ -0,0 +1,25 @@
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Minimal logger writing every record to stderr.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install the stderr logger, logging records up to `level`.
+pub fn init(level: LevelFilter) {
+    // Only fails if a logger is already installed, in which case that one is kept.
+    let _ = log::set_logger(&StderrLogger);
+    log::set_max_level(level);
+}
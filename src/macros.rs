@@ -0,0 +1,108 @@
+/// Implement `Display` and `FromStr` for an enum with an `ALL` list of its variants and a `name`
+/// for every variant. Names are parsed ignoring case and underscores, so both `not_certified`
+/// and the graphql `NotCertified` parse. With `serde`, the enum is also serialized as its name.
+macro_rules! name_impls {
+    ($ty:ident, $what:literal) => {
+        impl ::std::fmt::Display for $ty {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        impl ::std::str::FromStr for $ty {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let wanted = s.replace('_', "");
+                $ty::ALL
+                    .iter()
+                    .copied()
+                    .find(|v| v.name().replace('_', "").eq_ignore_ascii_case(&wanted))
+                    .ok_or_else(|| {
+                        format!(
+                            concat!("unknown ", $what, " \"{}\", expected one of: {}"),
+                            s,
+                            $ty::ALL
+                                .iter()
+                                .map(|v| v.name())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )
+                    })
+            }
+        }
+    };
+    ($ty:ident, $what:literal, serde) => {
+        name_impls!($ty, $what);
+
+        impl ::serde::Serialize for $ty {
+            fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.name())
+            }
+        }
+
+        impl<'de> ::serde::Deserialize<'de> for $ty {
+            fn deserialize<D: ::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                <String as ::serde::Deserialize>::deserialize(deserializer)?
+                    .parse()
+                    .map_err(::serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Level {
+        Low,
+        VeryHigh,
+    }
+
+    impl Level {
+        const ALL: [Level; 2] = [Level::Low, Level::VeryHigh];
+
+        fn name(&self) -> &'static str {
+            match self {
+                Level::Low => "low",
+                Level::VeryHigh => "very_high",
+            }
+        }
+    }
+
+    name_impls!(Level, "level", serde);
+
+    #[test]
+    fn names_parse_ignoring_case_and_underscores() {
+        for name in [
+            "very_high",
+            "VeryHigh",
+            "VERY_HIGH",
+            "veryhigh",
+            "very__high",
+        ] {
+            assert_eq!(name.parse(), Ok(Level::VeryHigh), "{name}");
+        }
+        assert_eq!("Low".parse(), Ok(Level::Low));
+        assert_eq!(
+            "very high".parse::<Level>(),
+            Err("unknown level \"very high\", expected one of: low, very_high".to_string())
+        );
+        assert_eq!(Level::VeryHigh.to_string(), "very_high");
+    }
+
+    #[test]
+    fn serde_uses_the_name() {
+        assert_eq!(
+            serde_json::to_string(&Level::VeryHigh).unwrap(),
+            r#""very_high""#
+        );
+        assert_eq!(
+            serde_json::from_str::<Level>(r#""VeryHigh""#).unwrap(),
+            Level::VeryHigh
+        );
+        assert!(serde_json::from_str::<Level>(r#""medium""#).is_err());
+    }
+}
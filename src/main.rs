@@ -1,149 +1,411 @@
-use std::{collections::HashSet, io::Write, time::Duration};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use chrono::{TimeZone, Utc};
-use serde::{de, Deserialize, Deserializer, Serialize};
-use serde_json::Value;
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
+use node_counter::{
+    Area, BoundingBox, Columns, Format, Granularity, Location, Network, Node, NodeCounter,
+    NodeCounterError, RawFetch, RetryPolicy, SnapshotStore, Source, DEFAULT_PAGE_SIZE,
+};
 
-const USER_AGENT: &str = "node_counter_agent";
-const MAINNET_GRAPHQL_URL: &str = "https://graphql.grid.tf/graphql";
+mod cmd;
+mod logger;
 
-const NODE_QUERY: &str = r#"
-query MyQuery {  nodes {    nodeID    created    farmID    resourcesTotal {      cru      hru      mru      sru    }  }}
-"#;
+/// Default output file of the `count` command, without the extension of the format.
+const COUNT_OUTPUT_FILE: &str = "node_count";
 
-const START_YEAR: i32 = 2022;
+/// Count the amount of nodes registered on the threefold grid over time.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
+    /// Command to run, defaults to `count`.
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Args)]
+struct GlobalArgs {
+    /// Network to query (mainnet, testnet, qanet, devnet).
+    #[arg(long, global = true, default_value_t = Network::Mainnet)]
+    network: Network,
+    /// Backend to fetch nodes from (graphql, gridproxy).
+    #[arg(long, global = true, default_value_t = Source::GraphQL)]
+    source: Source,
+    /// Custom endpoint, overriding the endpoint of the source on the network.
+    #[arg(long, global = true)]
+    endpoint: Option<String>,
+    /// Endpoint to fail over to when the endpoint keeps failing. Can be repeated, endpoints are
+    /// tried in the given order.
+    #[arg(long = "fallback-endpoint", global = true)]
+    fallback_endpoints: Vec<String>,
+    /// Amount of nodes and farms requested per page. Endpoints may return less per page.
+    #[arg(long, global = true, default_value_t = DEFAULT_PAGE_SIZE)]
+    page_size: u32,
+    /// Amount of retries per endpoint after network errors, 5xx and 429 responses.
+    #[arg(long, global = true, default_value_t = 3)]
+    retries: u32,
+    /// Backoff in seconds before the first retry. It doubles every retry, up to 60 seconds.
+    #[arg(long, global = true, default_value = "1", value_parser = parse_seconds)]
+    retry_backoff: Duration,
+    /// Only log warnings and errors.
+    #[arg(short, long, global = true)]
+    quiet: bool,
+    /// File to write output to. Use `-` for stdout.
+    #[arg(short, long, global = true)]
+    output: Option<PathBuf>,
+    /// Output format (csv, json, ndjson, markdown, geojson, kml, or parquet if enabled). Defaults
+    /// to csv.
+    #[arg(short, long, global = true)]
+    format: Option<Format>,
+    /// First day of the time range (YYYY-MM-DD).
+    #[arg(long, global = true)]
+    from: Option<NaiveDate>,
+    /// Last day of the time range (YYYY-MM-DD).
+    #[arg(long, global = true)]
+    to: Option<NaiveDate>,
+    /// Size of the periods (daily, weekly, monthly, quarterly, yearly).
+    #[arg(short, long, global = true, default_value_t = Granularity::Monthly)]
+    granularity: Granularity,
+    /// Include per period new nodes, farms and resources, and growth percentages.
+    #[arg(long, global = true)]
+    growth: bool,
+    /// Include per period node counts and resources of diy and certified nodes.
+    #[arg(long, global = true)]
+    certification: bool,
+    /// Include per period used and free resources, and the percentage of resources in use.
+    #[arg(long, global = true)]
+    utilisation: bool,
+    /// Include per period the amount of active, standby and down nodes, and the resources of
+    /// active nodes.
+    #[arg(long, global = true)]
+    activity: bool,
+    /// Hours after the last report of a node before it is counted as down.
+    #[arg(long, global = true, default_value = "24", value_parser = parse_hours)]
+    staleness: Duration,
+    /// Only use nodes within `--radius` km of this location (LAT,LON).
+    #[arg(long, global = true, requires = "radius", allow_hyphen_values = true)]
+    near: Option<Location>,
+    /// Radius around `--near` in km.
+    #[arg(long, global = true, requires = "near", value_parser = parse_radius)]
+    radius: Option<f64>,
+    /// Only use nodes inside this bounding box (SOUTH,WEST,NORTH,EAST).
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "near",
+        allow_hyphen_values = true
+    )]
+    bbox: Option<BoundingBox>,
+    /// SQLite snapshot store. Every fetch is saved in it.
+    #[arg(long, global = true)]
+    store: Option<PathBuf>,
+    /// Use the stored snapshot with this id instead of fetching. Requires `--store`.
+    #[arg(long, global = true, requires = "store", conflicts_with = "as_of")]
+    snapshot: Option<i64>,
+    /// Use the latest stored snapshot of the network taken on or before this day instead of
+    /// fetching (YYYY-MM-DD). Requires `--store`.
+    #[arg(long, global = true, requires = "store")]
+    as_of: Option<NaiveDate>,
+    /// Save the response bodies of the fetch exactly as received, with the fetch time, source
+    /// and endpoint, as JSON to this file. Files ending in `.gz` are gzip compressed.
+    #[arg(long, global = true)]
+    save_raw: Option<PathBuf>,
+    /// Read nodes from a file saved with `--save-raw` instead of fetching. Gzip compressed files
+    /// are accepted. Loaded nodes are not saved in the store, so `--store` is rejected.
+    #[arg(
+        long,
+        global = true,
+        conflicts_with_all = ["store", "snapshot", "as_of", "save_raw"]
+    )]
+    input: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Count nodes, farms and resources per period.
+    Count,
+    /// Dump all nodes as fetched from the network.
+    Fetch,
+    /// Count nodes and resources per farm and period.
+    Farms(cmd::farms::FarmsArgs),
+    /// Count nodes, farms and resources per country or continent and period.
+    Regions(cmd::regions::RegionsArgs),
+    /// Write the location of every node existing at the end of the time range.
+    Map,
+    /// Print a human readable summary of the grid growth over the time range.
+    Report,
+    /// Serve node counts over HTTP.
+    Serve(cmd::serve::ServeArgs),
+    /// Compare 2 sets of nodes, from `fetch` output or stored snapshots.
+    Diff(cmd::diff::DiffArgs),
+    /// List all snapshots in the store.
+    Snapshots,
+    /// Cross-check the nodes of the graphql indexer and the Grid Proxy.
+    Verify(cmd::verify::VerifyArgs),
+}
 
 #[tokio::main]
 async fn main() {
-    let client = reqwest::ClientBuilder::new()
-        .user_agent(USER_AGENT)
-        .gzip(true)
-        .timeout(Duration::from_secs(30))
-        .build()
-        .expect("Client config is valid");
-
-    let nodes = client
-        .post(MAINNET_GRAPHQL_URL)
-        .json(&GraphQLRequest::<()> {
-            operation_name: "list_nodes",
-            query: NODE_QUERY,
-            variables: None,
-        })
-        .send()
-        .await
-        .expect("Can send a graphql request and get a reply")
-        .json::<GraphQLResponse<NodeReply>>()
-        .await
-        .expect("Can parse response");
-
-    let mut file = std::fs::File::create("node_count.csv").expect("Can create file");
-
-    // header
-    writeln!(
-        file,
-        "date,node count,farms with nodes,total CRU,total MRU,total SRU,total HRU"
-    )
-    .unwrap();
-
-    // 10 years should be good
-    for year in START_YEAR..START_YEAR + 10 {
-        for month in 1..=12 {
-            let start = Utc
-                .with_ymd_and_hms(year, month, 1, 0, 0, 0)
-                .unwrap()
-                .timestamp();
-
-            if Utc::now().timestamp() < start {
-                break;
+    let cli = Cli::parse();
+    if let (Some(from), Some(to)) = (cli.global.from, cli.global.to) {
+        if from > to {
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    format!("--from {from} is later than --to {to}"),
+                )
+                .exit();
+        }
+    }
+    logger::init(if cli.global.quiet {
+        log::LevelFilter::Warn
+    } else {
+        log::LevelFilter::Info
+    });
+    if let Err(e) = run(cli).await {
+        eprintln!("Error: {e}");
+        std::process::exit(e.exit_code());
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), NodeCounterError> {
+    let global = cli.global;
+    match cli.command.unwrap_or(Command::Count) {
+        Command::Count => cmd::count::run(&global).await,
+        Command::Fetch => cmd::fetch::run(&global).await,
+        Command::Farms(args) => cmd::farms::run(&global, args).await,
+        Command::Regions(args) => cmd::regions::run(&global, args).await,
+        Command::Map => cmd::map::run(&global).await,
+        Command::Report => cmd::report::run(&global).await,
+        Command::Serve(args) => cmd::serve::run(&global, args).await,
+        Command::Diff(args) => cmd::diff::run(&global, args),
+        Command::Snapshots => cmd::snapshots::run(&global),
+        Command::Verify(args) => cmd::verify::run(&global, args).await,
+    }
+}
+
+impl GlobalArgs {
+    /// Build a [`NodeCounter`] from the global arguments.
+    fn counter(&self) -> Result<NodeCounter, NodeCounterError> {
+        let mut counter = NodeCounter::new()?
+            .network(self.network)
+            .source(self.source)
+            .granularity(self.granularity)
+            .page_size(self.page_size)
+            .staleness(self.staleness)
+            .activity(self.activity)
+            .fallback_endpoints(self.fallback_endpoints.clone())
+            .retry(RetryPolicy {
+                max_retries: self.retries,
+                initial_backoff: self.retry_backoff,
+                ..Default::default()
+            });
+        if let Some(endpoint) = &self.endpoint {
+            counter = counter.endpoint(endpoint);
+        }
+        if let Some(from) = self.from {
+            counter = counter.from(start_of_day(from));
+        }
+        if let Some(to) = self.to {
+            counter = counter.to(start_of_day(to));
+        }
+        if let Some(area) = self.area() {
+            counter = counter.area(area);
+        }
+        Ok(counter)
+    }
+
+    /// The area selected with `--near` and `--radius`, or `--bbox`.
+    fn area(&self) -> Option<Area> {
+        match (self.near, self.radius, self.bbox) {
+            (Some(center), Some(radius_km), _) => Some(Area::Radius { center, radius_km }),
+            (_, _, Some(bbox)) => Some(Area::BoundingBox(bbox)),
+            _ => None,
+        }
+    }
+
+    /// Get the nodes to work on, restricted to the selected area, together with the counter to
+    /// aggregate them. See [`GlobalArgs::all_nodes`].
+    async fn nodes(&self) -> Result<(NodeCounter, Vec<Node>), NodeCounterError> {
+        let (counter, nodes) = self.all_nodes().await?;
+        let nodes = counter.filter_area(nodes);
+        Ok((counter, nodes))
+    }
+
+    /// Get the nodes to work on, together with the counter to aggregate them.
+    ///
+    /// If an input file or a stored snapshot is selected, the nodes are loaded from it, and the
+    /// time range ends at the time the nodes were fetched unless set explicitly. Otherwise the
+    /// nodes are fetched, and saved as raw file and in the store if those are set.
+    async fn all_nodes(&self) -> Result<(NodeCounter, Vec<Node>), NodeCounterError> {
+        let mut counter = self.counter()?;
+
+        if let Some(input) = &self.input {
+            let raw = RawFetch::load(input)?;
+            counter = counter
+                .network(raw.network())
+                .source(raw.source())
+                .endpoint(raw.endpoint());
+            if self.to.is_none() {
+                counter = counter.to(raw.fetched_at());
+            }
+            return Ok((counter, raw.into_nodes()));
+        }
+
+        let mut store = self.store.as_deref().map(SnapshotStore::open).transpose()?;
+        if let Some(store) = &store {
+            let snapshot = match (self.snapshot, self.as_of) {
+                (Some(id), _) => Some(store.snapshot(id)?),
+                (None, Some(as_of)) => Some(store.snapshot_as_of(self.network, end_of_day(as_of))?),
+                (None, None) => None,
             };
+            if let Some(snapshot) = snapshot {
+                counter = counter
+                    .network(snapshot.network())
+                    .endpoint(snapshot.endpoint());
+                if self.to.is_none() {
+                    counter = counter.to(snapshot.taken_at());
+                }
+                let nodes = store.load(&snapshot)?;
+                return Ok((counter, nodes));
+            }
+        }
+
+        let (fetched_at, nodes) = match &self.save_raw {
+            Some(path) => {
+                let raw = counter.fetch_raw().await?;
+                raw.save(path)?;
+                (raw.fetched_at(), raw.into_nodes())
+            }
+            None => (Utc::now(), counter.fetch_nodes().await?),
+        };
+        if let Some(store) = &mut store {
+            let snapshot = store.save(
+                self.network,
+                counter.selected_endpoint(),
+                fetched_at,
+                &nodes,
+            )?;
+            log::info!(
+                "Saved snapshot {} with {} nodes",
+                snapshot.id(),
+                snapshot.node_count()
+            );
+        }
+
+        Ok((counter, nodes))
+    }
 
-            let (node_count, farms, total_resources) = nodes
-                .data
-                .nodes
-                .iter()
-                .filter(|node| node.created < start)
-                .fold(
-                    (
-                        0,
-                        HashSet::new(),
-                        Resources {
-                            cru: 0,
-                            mru: 0,
-                            sru: 0,
-                            hru: 0,
-                        },
-                    ),
-                    |(node_count, mut farms, mut resources), node| {
-                        farms.insert(node.farm_id);
-                        resources.cru += node.resources_total.cru;
-                        resources.mru += node.resources_total.mru;
-                        resources.hru += node.resources_total.hru;
-                        resources.sru += node.resources_total.sru;
-                        (node_count + 1, farms, resources)
-                    },
-                );
-
-            writeln!(
-                file,
-                "{year}-{month}-1,{node_count},{},{},{},{},{}",
-                farms.len(),
-                total_resources.cru,
-                total_resources.mru,
-                total_resources.sru,
-                total_resources.hru
-            )
-            .unwrap();
+    /// The selected output format, csv if none is selected.
+    fn format(&self) -> Format {
+        self.format.unwrap_or(Format::Csv)
+    }
+
+    /// Optional columns to include in aggregate output.
+    fn columns(&self) -> Columns {
+        Columns {
+            growth: self.growth,
+            certification: self.certification,
+            utilisation: self.utilisation,
+            activity: self.activity,
+        }
+    }
+
+    /// Fail if `command` can't write the selected format, before anything is fetched or written.
+    fn check_format(
+        &self,
+        command: &'static str,
+        supported: fn(&Format) -> bool,
+    ) -> Result<(), NodeCounterError> {
+        let format = self.format();
+        if supported(&format) {
+            Ok(())
+        } else {
+            Err(NodeCounterError::UnsupportedFormat { format, command })
+        }
+    }
+
+    /// Open the output, using `default` if no output is set. If neither is set, or the output is
+    /// `-`, stdout is used.
+    fn open_output(&self, default: Option<&str>) -> Result<Output, NodeCounterError> {
+        let path = self.output.clone().or_else(|| default.map(PathBuf::from));
+        match path {
+            Some(path) if path != Path::new("-") => {
+                let file = File::create(&path).map_err(|source| NodeCounterError::Io {
+                    path: path.clone(),
+                    source,
+                })?;
+                Ok(Output {
+                    path,
+                    writer: Box::new(BufWriter::new(file)),
+                })
+            }
+            _ => Ok(Output {
+                path: PathBuf::from("<stdout>"),
+                writer: Box::new(io::stdout().lock()),
+            }),
         }
     }
 }
 
-#[derive(Serialize)]
-pub struct GraphQLRequest<'a, T: Serialize> {
-    operation_name: &'a str,
-    query: &'a str,
-    variables: Option<T>,
+/// An opened output, keeping track of its path for error reporting.
+struct Output {
+    path: PathBuf,
+    writer: Box<dyn Write>,
 }
 
-#[derive(Deserialize)]
-pub struct GraphQLResponse<T> {
-    data: T,
+impl Output {
+    /// Write to the output with the given function, and flush it afterwards.
+    fn write_with(
+        mut self,
+        f: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+    ) -> Result<(), NodeCounterError> {
+        f(&mut self.writer)
+            .and_then(|_| self.writer.flush())
+            .map_err(|source| NodeCounterError::Io {
+                path: self.path,
+                source,
+            })
+    }
 }
 
-#[derive(Deserialize)]
-pub struct NodeReply {
-    nodes: Vec<Node>,
+/// Parse a non-negative amount of seconds, which may have a fraction.
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    parse_duration(s, 1., "seconds")
+}
+
+/// Parse a non-negative amount of hours, which may have a fraction.
+fn parse_hours(s: &str) -> Result<Duration, String> {
+    parse_duration(s, 3600., "hours")
+}
+
+/// Parse a positive, finite radius in km.
+fn parse_radius(s: &str) -> Result<f64, String> {
+    let radius = s.parse::<f64>().map_err(|e| e.to_string())?;
+    if radius.is_finite() && radius > 0. {
+        Ok(radius)
+    } else {
+        Err(format!("expected a positive amount of km, got {s}"))
+    }
 }
 
-#[derive(Deserialize)]
-pub struct Node {
-    #[serde(rename = "nodeID")]
-    _node_id: u32,
-    #[serde(rename = "farmID")]
-    farm_id: u32,
-    created: i64,
-    #[serde(rename = "resourcesTotal")]
-    resources_total: Resources,
+/// Parse a non-negative amount of `unit`s, each `unit_secs` seconds long.
+fn parse_duration(s: &str, unit_secs: f64, unit: &str) -> Result<Duration, String> {
+    let amount = s.parse::<f64>().map_err(|e| e.to_string())?;
+    Duration::try_from_secs_f64(amount * unit_secs)
+        .map_err(|_| format!("expected a non-negative amount of {unit}, got {s}"))
 }
 
-#[derive(Deserialize)]
-pub struct Resources {
-    #[serde(deserialize_with = "de_u64")]
-    cru: u64,
-    #[serde(deserialize_with = "de_u64")]
-    mru: u64,
-    #[serde(deserialize_with = "de_u64")]
-    sru: u64,
-    #[serde(deserialize_with = "de_u64")]
-    hru: u64,
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap().and_utc()
 }
 
-/// Helper function to deserialize an u64 which is returned as string (BigNum) in graphql.
-pub fn de_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
-    Ok(match Value::deserialize(deserializer)? {
-        Value::String(s) => s.parse().map_err(de::Error::custom)?,
-        Value::Number(num) => num
-            .as_u64()
-            .ok_or_else(|| de::Error::custom("Invalid number"))?,
-        _ => return Err(de::Error::custom("wrong type")),
-    })
+fn end_of_day(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(23, 59, 59).unwrap().and_utc()
 }
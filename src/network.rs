@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+/// A threefold grid network.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+    Qanet,
+    Devnet,
+}
+
+impl Network {
+    /// All known networks.
+    pub const ALL: [Network; 4] = [
+        Network::Mainnet,
+        Network::Testnet,
+        Network::Qanet,
+        Network::Devnet,
+    ];
+
+    /// The graphql endpoint of the network.
+    pub fn graphql_url(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "https://graphql.grid.tf/graphql",
+            Network::Testnet => "https://graphql.test.grid.tf/graphql",
+            Network::Qanet => "https://graphql.qa.grid.tf/graphql",
+            Network::Devnet => "https://graphql.dev.grid.tf/graphql",
+        }
+    }
+
+    /// The Grid Proxy endpoint of the network.
+    pub fn grid_proxy_url(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "https://gridproxy.grid.tf",
+            Network::Testnet => "https://gridproxy.test.grid.tf",
+            Network::Qanet => "https://gridproxy.qa.grid.tf",
+            Network::Devnet => "https://gridproxy.dev.grid.tf",
+        }
+    }
+
+    /// Short lowercase name of the network.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Qanet => "qanet",
+            Network::Devnet => "devnet",
+        }
+    }
+}
+
+name_impls!(Network, "network");
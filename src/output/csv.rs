@@ -0,0 +1,49 @@
+use std::io;
+
+use serde_json::{Map, Value};
+
+use super::{Metadata, RowWriter};
+
+/// Comma separated values, with a header line containing the column names.
+pub struct CsvWriter;
+
+impl RowWriter for CsvWriter {
+    fn write_rows(
+        &self,
+        w: &mut dyn io::Write,
+        _: &Metadata,
+        columns: &[String],
+        rows: &[Map<String, Value>],
+    ) -> io::Result<()> {
+        if columns.is_empty() {
+            return Ok(());
+        }
+
+        // header
+        let header = columns.iter().map(|k| escape(k)).collect::<Vec<_>>();
+        writeln!(w, "{}", header.join(","))?;
+
+        for row in rows {
+            let values = columns
+                .iter()
+                .map(|column| match row.get(column) {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(s)) => escape(s),
+                    Some(other) => escape(&other.to_string()),
+                })
+                .collect::<Vec<_>>();
+            writeln!(w, "{}", values.join(","))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Quote a field if it contains a separator, quote or newline.
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
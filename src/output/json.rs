@@ -0,0 +1,47 @@
+use std::io;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::{Metadata, RowWriter};
+
+/// A single JSON document, with the metadata and all rows.
+pub struct JsonWriter;
+
+#[derive(Serialize)]
+struct Document<'a> {
+    metadata: &'a Metadata,
+    rows: &'a [Map<String, Value>],
+}
+
+impl RowWriter for JsonWriter {
+    fn write_rows(
+        &self,
+        w: &mut dyn io::Write,
+        metadata: &Metadata,
+        _: &[String],
+        rows: &[Map<String, Value>],
+    ) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *w, &Document { metadata, rows })?;
+        writeln!(w)
+    }
+}
+
+/// Newline delimited JSON, one row per line.
+pub struct NdjsonWriter;
+
+impl RowWriter for NdjsonWriter {
+    fn write_rows(
+        &self,
+        w: &mut dyn io::Write,
+        _: &Metadata,
+        _: &[String],
+        rows: &[Map<String, Value>],
+    ) -> io::Result<()> {
+        for row in rows {
+            serde_json::to_writer(&mut *w, row)?;
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use std::io;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::{Metadata, RowWriter};
+
+/// A GeoJSON feature collection, with a point feature per row. The metadata is included as a
+/// foreign `metadata` member.
+pub struct GeoJsonWriter;
+
+#[derive(Serialize)]
+struct FeatureCollection<'a> {
+    r#type: &'static str,
+    metadata: &'a Metadata,
+    features: Vec<Feature>,
+}
+
+#[derive(Serialize)]
+struct Feature {
+    r#type: &'static str,
+    geometry: Point,
+    properties: Map<String, Value>,
+}
+
+#[derive(Serialize)]
+struct Point {
+    r#type: &'static str,
+    /// Longitude and latitude, in that order.
+    coordinates: [f64; 2],
+}
+
+impl RowWriter for GeoJsonWriter {
+    fn write_rows(
+        &self,
+        w: &mut dyn io::Write,
+        metadata: &Metadata,
+        columns: &[String],
+        rows: &[Map<String, Value>],
+    ) -> io::Result<()> {
+        let features = located(columns, rows, "geojson")?
+            .map(|(longitude, latitude, properties)| Feature {
+                r#type: "Feature",
+                geometry: Point {
+                    r#type: "Point",
+                    coordinates: [longitude, latitude],
+                },
+                properties,
+            })
+            .collect();
+        let collection = FeatureCollection {
+            r#type: "FeatureCollection",
+            metadata,
+            features,
+        };
+        serde_json::to_writer_pretty(&mut *w, &collection)?;
+        writeln!(w)
+    }
+}
+
+/// A KML document, with a placemark per row. Rows with a `created` column get a timestamp, so
+/// viewers can show the grid at any point in time.
+pub struct KmlWriter;
+
+impl RowWriter for KmlWriter {
+    fn write_rows(
+        &self,
+        w: &mut dyn io::Write,
+        metadata: &Metadata,
+        columns: &[String],
+        rows: &[Map<String, Value>],
+    ) -> io::Result<()> {
+        let placemarks = located(columns, rows, "kml")?;
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(w, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+        writeln!(w, "<Document>")?;
+        writeln!(w, "  <name>{} nodes</name>", metadata.network())?;
+
+        for (longitude, latitude, properties) in placemarks {
+            writeln!(w, "  <Placemark>")?;
+            if let Some(name) = properties.values().next() {
+                writeln!(w, "    <name>{}</name>", escape(&text(name)))?;
+            }
+            if let Some(Value::String(created)) = properties.get("created") {
+                writeln!(
+                    w,
+                    "    <TimeStamp><when>{}</when></TimeStamp>",
+                    escape(created)
+                )?;
+            }
+            writeln!(w, "    <ExtendedData>")?;
+            for (key, value) in properties.iter().filter(|(_, v)| !v.is_null()) {
+                writeln!(
+                    w,
+                    r#"      <Data name="{}"><value>{}</value></Data>"#,
+                    escape(key),
+                    escape(&text(value))
+                )?;
+            }
+            writeln!(w, "    </ExtendedData>")?;
+            writeln!(
+                w,
+                "    <Point><coordinates>{longitude},{latitude}</coordinates></Point>"
+            )?;
+            writeln!(w, "  </Placemark>")?;
+        }
+
+        writeln!(w, "</Document>")?;
+        writeln!(w, "</kml>")
+    }
+}
+
+/// Split the rows with a location into their longitude, latitude and remaining columns. Rows
+/// without a location are skipped, but the columns must exist.
+fn located<'a>(
+    columns: &[String],
+    rows: &'a [Map<String, Value>],
+    format: &str,
+) -> io::Result<impl Iterator<Item = (f64, f64, Map<String, Value>)> + 'a> {
+    let missing = |name: &str| !columns.iter().any(|c| c == name);
+    if !columns.is_empty() && (missing("latitude") || missing("longitude")) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{format} output is only supported for nodes"),
+        ));
+    }
+
+    Ok(rows.iter().filter_map(|row| {
+        let latitude = row.get("latitude")?.as_f64()?;
+        let longitude = row.get("longitude")?.as_f64()?;
+        let properties = row
+            .iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "latitude" | "longitude"))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        Some((longitude, latitude, properties))
+    }))
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
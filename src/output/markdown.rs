@@ -0,0 +1,45 @@
+use std::io;
+
+use serde_json::{Map, Value};
+
+use super::{Metadata, RowWriter};
+
+/// A markdown table.
+pub struct MarkdownWriter;
+
+impl RowWriter for MarkdownWriter {
+    fn write_rows(
+        &self,
+        w: &mut dyn io::Write,
+        _: &Metadata,
+        columns: &[String],
+        rows: &[Map<String, Value>],
+    ) -> io::Result<()> {
+        if columns.is_empty() {
+            return Ok(());
+        }
+
+        let header = columns.iter().map(|k| escape(k)).collect::<Vec<_>>();
+        writeln!(w, "| {} |", header.join(" | "))?;
+        writeln!(w, "|{}", "---|".repeat(header.len()))?;
+
+        for row in rows {
+            let values = columns
+                .iter()
+                .map(|column| match row.get(column) {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(s)) => escape(s),
+                    Some(other) => other.to_string(),
+                })
+                .collect::<Vec<_>>();
+            writeln!(w, "| {} |", values.join(" | "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Escape pipes so they don't end a cell.
+fn escape(cell: &str) -> String {
+    cell.replace('|', "\\|")
+}
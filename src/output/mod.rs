@@ -0,0 +1,884 @@
+use std::{collections::BTreeMap, io};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    Area, Continent, FarmAggregate, FarmCertification, Granularity, Location, Network, Node,
+    NodeCertification, NodeStatus, NodeTotals, Period, PeriodAggregate, Region, RegionAggregate,
+    Resources,
+};
+
+mod csv;
+mod json;
+mod map;
+mod markdown;
+#[cfg(feature = "parquet")]
+mod parquet;
+
+/// Output format for aggregates and nodes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Csv,
+    Json,
+    Ndjson,
+    Markdown,
+    /// GeoJSON, only supported for nodes.
+    GeoJson,
+    /// KML, only supported for nodes.
+    Kml,
+    /// Apache Parquet, only available with the `parquet` feature.
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl Format {
+    /// All supported formats.
+    pub const ALL: &'static [Format] = &[
+        Format::Csv,
+        Format::Json,
+        Format::Ndjson,
+        Format::Markdown,
+        Format::GeoJson,
+        Format::Kml,
+        #[cfg(feature = "parquet")]
+        Format::Parquet,
+    ];
+
+    /// Short lowercase name of the format.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Ndjson => "ndjson",
+            Format::Markdown => "markdown",
+            Format::GeoJson => "geojson",
+            Format::Kml => "kml",
+            #[cfg(feature = "parquet")]
+            Format::Parquet => "parquet",
+        }
+    }
+
+    /// The MIME type of the format.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv",
+            Format::Json => "application/json",
+            Format::Ndjson => "application/x-ndjson",
+            Format::Markdown => "text/markdown",
+            Format::GeoJson => "application/geo+json",
+            Format::Kml => "application/vnd.google-earth.kml+xml",
+            #[cfg(feature = "parquet")]
+            Format::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    /// The usual file extension of the format, without dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Ndjson => "ndjson",
+            Format::Markdown => "md",
+            Format::GeoJson => "geojson",
+            Format::Kml => "kml",
+            #[cfg(feature = "parquet")]
+            Format::Parquet => "parquet",
+        }
+    }
+
+    /// Whether aggregates can be written in this format. The map formats only support nodes.
+    pub fn supports_aggregates(&self) -> bool {
+        !matches!(self, Format::GeoJson | Format::Kml)
+    }
+
+    /// Whether generic rows, such as farm and region aggregates, can be written in this format.
+    /// The map formats need a location, and parquet needs typed columns.
+    pub fn supports_rows(&self) -> bool {
+        match self {
+            Format::GeoJson | Format::Kml => false,
+            #[cfg(feature = "parquet")]
+            Format::Parquet => false,
+            _ => true,
+        }
+    }
+
+    /// The writer for this format.
+    pub fn writer(&self) -> &'static dyn RowWriter {
+        match self {
+            Format::Csv => &csv::CsvWriter,
+            Format::Json => &json::JsonWriter,
+            Format::Ndjson => &json::NdjsonWriter,
+            Format::Markdown => &markdown::MarkdownWriter,
+            Format::GeoJson => &map::GeoJsonWriter,
+            Format::Kml => &map::KmlWriter,
+            #[cfg(feature = "parquet")]
+            Format::Parquet => &parquet::ParquetWriter,
+        }
+    }
+}
+
+name_impls!(Format, "format");
+
+/// Writes rows in a specific format.
+///
+/// Rows are passed as JSON objects. All rows are expected to have the given columns, which are
+/// passed separately so formats with a header can write it even without rows.
+pub trait RowWriter {
+    fn write_rows(
+        &self,
+        w: &mut dyn io::Write,
+        metadata: &Metadata,
+        columns: &[String],
+        rows: &[Map<String, Value>],
+    ) -> io::Result<()>;
+}
+
+/// Information about how the output was generated. Only formats which support a header include
+/// this.
+#[derive(Debug, Clone, Serialize)]
+pub struct Metadata {
+    network: Network,
+    endpoint: String,
+    generated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<Query>,
+}
+
+/// The parameters used to aggregate the nodes.
+#[derive(Debug, Clone, Serialize)]
+pub struct Query {
+    pub granularity: Granularity,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub area: Option<Area>,
+}
+
+impl Metadata {
+    /// Create new `Metadata` for output generated now.
+    pub fn new(network: Network, endpoint: impl Into<String>) -> Self {
+        Self {
+            network,
+            endpoint: endpoint.into(),
+            generated_at: Utc::now(),
+            query: None,
+        }
+    }
+
+    /// Attach the query parameters.
+    pub fn with_query(mut self, query: Query) -> Self {
+        self.query = Some(query);
+        self
+    }
+
+    /// The network the data was fetched from.
+    pub fn network(&self) -> Network {
+        self.network
+    }
+}
+
+/// How per farm aggregates are laid out.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// One row per period and farm.
+    #[default]
+    Long,
+    /// One row per period, with the columns of every farm side by side.
+    Wide,
+}
+
+impl Layout {
+    /// All layouts.
+    pub const ALL: [Layout; 2] = [Layout::Long, Layout::Wide];
+
+    /// Short lowercase name of the layout.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layout::Long => "long",
+            Layout::Wide => "wide",
+        }
+    }
+}
+
+name_impls!(Layout, "layout");
+
+/// Optional columns to include when writing aggregates.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Columns {
+    /// Include new nodes, farms and resources per period, and growth percentages.
+    pub growth: bool,
+    /// Include node counts and resources per node certification.
+    pub certification: bool,
+    /// Include used and free resources, and the percentage of resources in use.
+    pub utilisation: bool,
+    /// Include the amount of active, standby and down nodes, and the resources of active nodes.
+    pub activity: bool,
+}
+
+/// A single period of aggregated nodes, as written to the output.
+#[derive(Debug, Clone, Serialize)]
+pub struct AggregateRow {
+    #[serde(rename = "date")]
+    date: NaiveDate,
+    #[serde(rename = "period end")]
+    period_end: NaiveDate,
+    #[serde(rename = "node count")]
+    node_count: u64,
+    #[serde(rename = "farms with nodes")]
+    farms: u64,
+    #[serde(flatten, with = "total_resources")]
+    resources: Resources,
+    #[serde(rename = "network")]
+    network: Network,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    growth: Option<GrowthColumns>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    certification: Option<CertificationColumns>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    utilisation: Option<UtilisationColumns>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    activity: Option<ActivityColumns>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct GrowthColumns {
+    #[serde(rename = "new nodes")]
+    new_nodes: u64,
+    #[serde(rename = "new farms")]
+    new_farms: u64,
+    #[serde(flatten, with = "new_resources")]
+    new_resources: Resources,
+    #[serde(rename = "MoM growth %")]
+    mom_growth: Option<f64>,
+    #[serde(rename = "YoY growth %")]
+    yoy_growth: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct CertificationColumns {
+    #[serde(rename = "diy nodes")]
+    diy_nodes: u64,
+    #[serde(flatten, with = "diy_resources")]
+    diy_resources: Resources,
+    #[serde(rename = "certified nodes")]
+    certified_nodes: u64,
+    #[serde(flatten, with = "certified_resources")]
+    certified_resources: Resources,
+}
+
+/// Resources in use, relative to the nodes which reported their usage. Nodes without a known
+/// usage are left out entirely, so they don't count as free. Without any such node, all columns
+/// are null.
+#[derive(Debug, Clone, Serialize)]
+struct UtilisationColumns {
+    #[serde(flatten, with = "used_resources::option")]
+    used: Option<Resources>,
+    #[serde(flatten, with = "free_resources::option")]
+    free: Option<Resources>,
+    #[serde(rename = "CRU used %")]
+    cru: Option<f64>,
+    #[serde(rename = "MRU used %")]
+    mru: Option<f64>,
+    #[serde(rename = "SRU used %")]
+    sru: Option<f64>,
+    #[serde(rename = "HRU used %")]
+    hru: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ActivityColumns {
+    #[serde(rename = "active nodes")]
+    active_nodes: u64,
+    #[serde(rename = "standby nodes")]
+    standby_nodes: u64,
+    #[serde(rename = "down nodes")]
+    down_nodes: u64,
+    #[serde(flatten, with = "active_resources")]
+    active_resources: Resources,
+}
+
+impl UtilisationColumns {
+    fn new(reporting: &NodeTotals, used: &Resources) -> Self {
+        let reported = reporting.node_count() > 0;
+        let total = reporting.resources();
+        // Percentages are unknown for resources the nodes don't have.
+        let percentage = |used: u64, total: u64| {
+            (total > 0).then(|| round_percentage(used as f64 / total as f64 * 100.))
+        };
+        Self {
+            used: reported.then_some(*used),
+            free: reported.then(|| total.saturating_sub(used)),
+            cru: percentage(used.cru(), total.cru()),
+            mru: percentage(used.mru(), total.mru()),
+            sru: percentage(used.sru(), total.sru()),
+            hru: percentage(used.hru(), total.hru()),
+        }
+    }
+}
+
+impl AggregateRow {
+    /// Create the row for an aggregated period, including the requested optional columns.
+    pub fn new(aggregate: &PeriodAggregate, network: Network, columns: Columns) -> Self {
+        Self {
+            date: aggregate.start().date_naive(),
+            period_end: aggregate.end().date_naive(),
+            node_count: aggregate.node_count(),
+            farms: aggregate.farms(),
+            resources: *aggregate.resources(),
+            network,
+            growth: columns.growth.then(|| GrowthColumns {
+                new_nodes: aggregate.new_nodes(),
+                new_farms: aggregate.new_farms(),
+                new_resources: *aggregate.new_resources(),
+                mom_growth: aggregate.mom_growth().map(round_percentage),
+                yoy_growth: aggregate.yoy_growth().map(round_percentage),
+            }),
+            certification: columns.certification.then(|| {
+                let diy = aggregate.by_certification(NodeCertification::Diy);
+                let certified = aggregate.by_certification(NodeCertification::Certified);
+                CertificationColumns {
+                    diy_nodes: diy.node_count(),
+                    diy_resources: *diy.resources(),
+                    certified_nodes: certified.node_count(),
+                    certified_resources: *certified.resources(),
+                }
+            }),
+            utilisation: columns.utilisation.then(|| {
+                UtilisationColumns::new(aggregate.reporting_usage(), aggregate.used_resources())
+            }),
+            activity: columns.activity.then(|| {
+                let active = aggregate.by_status(NodeStatus::Up);
+                ActivityColumns {
+                    active_nodes: active.node_count(),
+                    standby_nodes: aggregate.by_status(NodeStatus::Standby).node_count(),
+                    down_nodes: aggregate.by_status(NodeStatus::Down).node_count(),
+                    active_resources: *active.resources(),
+                }
+            }),
+        }
+    }
+}
+
+/// A single period of a single farm, as written to the output in long layout.
+#[derive(Debug, Clone, Serialize)]
+pub struct FarmRow {
+    #[serde(rename = "date")]
+    date: NaiveDate,
+    #[serde(rename = "period end")]
+    period_end: NaiveDate,
+    #[serde(rename = "farm id")]
+    farm_id: u32,
+    #[serde(rename = "node count")]
+    node_count: u64,
+    #[serde(flatten, with = "total_resources")]
+    resources: Resources,
+    #[serde(rename = "network")]
+    network: Network,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    utilisation: Option<UtilisationColumns>,
+}
+
+impl FarmRow {
+    /// Create the row for a farm in an aggregated period. Of the optional columns, only
+    /// utilisation applies to farms.
+    pub fn new(aggregate: &FarmAggregate, network: Network, columns: Columns) -> Self {
+        Self {
+            date: aggregate.start().date_naive(),
+            period_end: aggregate.end().date_naive(),
+            farm_id: aggregate.farm_id(),
+            node_count: aggregate.node_count(),
+            resources: *aggregate.resources(),
+            network,
+            utilisation: columns.utilisation.then(|| {
+                UtilisationColumns::new(aggregate.reporting_usage(), aggregate.used_resources())
+            }),
+        }
+    }
+}
+
+/// A single period of a single country or continent, as written to the output.
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionRow {
+    #[serde(rename = "date")]
+    date: NaiveDate,
+    #[serde(rename = "period end")]
+    period_end: NaiveDate,
+    #[serde(rename = "continent")]
+    continent: Option<Continent>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    country: Option<CountryColumns>,
+    #[serde(rename = "node count")]
+    node_count: u64,
+    #[serde(rename = "farms with nodes")]
+    farms: u64,
+    #[serde(flatten, with = "total_resources")]
+    resources: Resources,
+    #[serde(rename = "network")]
+    network: Network,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct CountryColumns {
+    #[serde(rename = "country code")]
+    code: Option<&'static str>,
+    #[serde(rename = "country")]
+    name: Option<&'static str>,
+}
+
+impl RegionRow {
+    /// Create the row for a region in an aggregated period. The country columns are only
+    /// included when grouping by country.
+    pub fn new(aggregate: &RegionAggregate, network: Network, region: Region) -> Self {
+        Self {
+            date: aggregate.start().date_naive(),
+            period_end: aggregate.end().date_naive(),
+            continent: aggregate.continent(),
+            country: (region == Region::Country).then(|| CountryColumns {
+                code: aggregate.country().map(|c| c.code()),
+                name: aggregate.country().map(|c| c.name()),
+            }),
+            node_count: aggregate.node_count(),
+            farms: aggregate.farms(),
+            resources: *aggregate.resources(),
+            network,
+        }
+    }
+}
+
+/// A single node, as written to the output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeRow {
+    #[serde(rename = "node id")]
+    node_id: u32,
+    #[serde(rename = "farm id")]
+    farm_id: u32,
+    #[serde(rename = "created")]
+    created: DateTime<Utc>,
+    #[serde(flatten, with = "plain_resources")]
+    resources: Resources,
+    #[serde(rename = "used CRU", default)]
+    used_cru: Option<u64>,
+    #[serde(rename = "used MRU", default)]
+    used_mru: Option<u64>,
+    #[serde(rename = "used SRU", default)]
+    used_sru: Option<u64>,
+    #[serde(rename = "used HRU", default)]
+    used_hru: Option<u64>,
+    #[serde(rename = "country", default)]
+    country: Option<String>,
+    #[serde(rename = "city", default)]
+    city: Option<String>,
+    #[serde(rename = "latitude", default)]
+    latitude: Option<f64>,
+    #[serde(rename = "longitude", default)]
+    longitude: Option<f64>,
+    #[serde(rename = "certification", default)]
+    certification: Option<NodeCertification>,
+    #[serde(rename = "farm certification", default)]
+    farm_certification: Option<FarmCertification>,
+    #[serde(rename = "dedicated farm", default)]
+    dedicated_farm: Option<bool>,
+    #[serde(rename = "updated at", default)]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "status", default)]
+    status: Option<NodeStatus>,
+}
+
+impl From<&Node> for NodeRow {
+    fn from(node: &Node) -> Self {
+        Self {
+            node_id: node.node_id(),
+            farm_id: node.farm_id(),
+            created: DateTime::from_timestamp(node.created(), 0).unwrap_or_default(),
+            resources: *node.resources_total(),
+            used_cru: node.resources_used().map(Resources::cru),
+            used_mru: node.resources_used().map(Resources::mru),
+            used_sru: node.resources_used().map(Resources::sru),
+            used_hru: node.resources_used().map(Resources::hru),
+            country: node.country().map(str::to_string),
+            city: node.city().map(str::to_string),
+            latitude: node.location().map(|l| l.latitude()),
+            longitude: node.location().map(|l| l.longitude()),
+            certification: node.certification(),
+            farm_certification: node.farm_certification(),
+            dedicated_farm: node.dedicated_farm(),
+            updated_at: node
+                .updated_at()
+                .and_then(|updated_at| DateTime::from_timestamp(updated_at, 0)),
+            status: node.status(),
+        }
+    }
+}
+
+impl From<NodeRow> for Node {
+    fn from(row: NodeRow) -> Self {
+        let location = match (row.latitude, row.longitude) {
+            (Some(latitude), Some(longitude)) => Location::new(latitude, longitude),
+            _ => None,
+        };
+        let resources_used = match (row.used_cru, row.used_mru, row.used_sru, row.used_hru) {
+            (Some(cru), Some(mru), Some(sru), Some(hru)) => {
+                Some(Resources::new(cru, mru, sru, hru))
+            }
+            _ => None,
+        };
+        Node::new(
+            row.node_id,
+            row.farm_id,
+            row.created.timestamp(),
+            row.resources,
+        )
+        .with_resources_used(resources_used)
+        .with_location(row.country, row.city, location)
+        .with_certification(row.certification)
+        .with_farm(row.farm_certification, row.dedicated_farm)
+        .with_activity(row.updated_at.map(|u| u.timestamp()), row.status)
+    }
+}
+
+/// Generate a module to (de)serialize [`Resources`] as flat columns with the given names. Its
+/// `option` module serializes unknown resources as null columns.
+macro_rules! resource_columns {
+    ($module:ident, $cru:literal, $mru:literal, $sru:literal, $hru:literal) => {
+        mod $module {
+            use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+            use crate::Resources;
+
+            #[derive(Serialize, Deserialize)]
+            struct Columns<T> {
+                #[serde(rename = $cru)]
+                cru: T,
+                #[serde(rename = $mru)]
+                mru: T,
+                #[serde(rename = $sru)]
+                sru: T,
+                #[serde(rename = $hru)]
+                hru: T,
+            }
+
+            #[allow(dead_code)]
+            pub fn serialize<S: Serializer>(r: &Resources, s: S) -> Result<S::Ok, S::Error> {
+                Columns {
+                    cru: r.cru(),
+                    mru: r.mru(),
+                    sru: r.sru(),
+                    hru: r.hru(),
+                }
+                .serialize(s)
+            }
+
+            #[allow(dead_code)]
+            pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Resources, D::Error> {
+                let c = Columns::deserialize(d)?;
+                Ok(Resources::new(c.cru, c.mru, c.sru, c.hru))
+            }
+
+            #[allow(dead_code)]
+            pub mod option {
+                use serde::{Serialize, Serializer};
+
+                use super::Columns;
+                use crate::Resources;
+
+                pub fn serialize<S: Serializer>(
+                    r: &Option<Resources>,
+                    s: S,
+                ) -> Result<S::Ok, S::Error> {
+                    Columns {
+                        cru: r.map(|r| r.cru()),
+                        mru: r.map(|r| r.mru()),
+                        sru: r.map(|r| r.sru()),
+                        hru: r.map(|r| r.hru()),
+                    }
+                    .serialize(s)
+                }
+            }
+        }
+    };
+}
+
+resource_columns!(
+    total_resources,
+    "total CRU",
+    "total MRU",
+    "total SRU",
+    "total HRU"
+);
+resource_columns!(new_resources, "new CRU", "new MRU", "new SRU", "new HRU");
+resource_columns!(plain_resources, "CRU", "MRU", "SRU", "HRU");
+resource_columns!(
+    used_resources,
+    "used CRU",
+    "used MRU",
+    "used SRU",
+    "used HRU"
+);
+resource_columns!(
+    free_resources,
+    "free CRU",
+    "free MRU",
+    "free SRU",
+    "free HRU"
+);
+resource_columns!(diy_resources, "diy CRU", "diy MRU", "diy SRU", "diy HRU");
+resource_columns!(
+    certified_resources,
+    "certified CRU",
+    "certified MRU",
+    "certified SRU",
+    "certified HRU"
+);
+
+resource_columns!(
+    active_resources,
+    "active CRU",
+    "active MRU",
+    "active SRU",
+    "active HRU"
+);
+
+/// Round a percentage to 2 decimals.
+fn round_percentage(percentage: f64) -> f64 {
+    (percentage * 100.).round() / 100.
+}
+
+/// Write rows in the given format. The columns are taken from the first row, or from the default
+/// row without rows, so the header is always written.
+pub fn write_rows<R: Serialize + Default>(
+    w: &mut dyn io::Write,
+    format: Format,
+    metadata: &Metadata,
+    rows: &[R],
+) -> io::Result<()> {
+    write_rows_like(w, format, metadata, &R::default(), rows)
+}
+
+/// Write rows in the given format. Without rows, the columns are taken from `template`, so the
+/// header is still written.
+fn write_rows_like<R: Serialize>(
+    w: &mut dyn io::Write,
+    format: Format,
+    metadata: &Metadata,
+    template: &R,
+    rows: &[R],
+) -> io::Result<()> {
+    let rows = rows.iter().map(to_object).collect::<io::Result<Vec<_>>>()?;
+    let columns: Vec<_> = match rows.first() {
+        Some(row) => row.keys().cloned().collect(),
+        None => to_object(template)?.into_iter().map(|(k, _)| k).collect(),
+    };
+
+    format.writer().write_rows(w, metadata, &columns, &rows)
+}
+
+/// Serialize a row to a JSON object, with its columns in order.
+fn to_object<R: Serialize>(row: &R) -> io::Result<Map<String, Value>> {
+    match serde_json::to_value(row)? {
+        Value::Object(map) => Ok(map),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "rows must serialize to an object",
+        )),
+    }
+}
+
+/// Write aggregated periods in the given format.
+pub fn write_aggregates(
+    w: &mut dyn io::Write,
+    format: Format,
+    metadata: &Metadata,
+    columns: Columns,
+    rows: &[PeriodAggregate],
+) -> io::Result<()> {
+    let rows = rows
+        .iter()
+        .map(|row| AggregateRow::new(row, metadata.network(), columns))
+        .collect::<Vec<_>>();
+    #[cfg(feature = "parquet")]
+    if format == Format::Parquet {
+        return parquet::write_aggregates(w, &rows, columns);
+    }
+    let template = AggregateRow::new(&PeriodAggregate::default(), metadata.network(), columns);
+    write_rows_like(w, format, metadata, &template, &rows)
+}
+
+/// Write per farm aggregates in the given format and layout.
+///
+/// In wide layout, every period in `periods` gets a row, and every farm present in any period gets
+/// a node count and resource columns, named after the farm id. Farms without nodes in a period have
+/// zeroes in that period, and null utilisation.
+pub fn write_farm_aggregates(
+    w: &mut dyn io::Write,
+    format: Format,
+    metadata: &Metadata,
+    layout: Layout,
+    columns: Columns,
+    periods: &[Period],
+    rows: &[FarmAggregate],
+) -> io::Result<()> {
+    let rows = rows
+        .iter()
+        .map(|row| FarmRow::new(row, metadata.network(), columns))
+        .collect::<Vec<_>>();
+    match layout {
+        Layout::Long => {
+            let template = FarmRow::new(&FarmAggregate::default(), metadata.network(), columns);
+            write_rows_like(w, format, metadata, &template, &rows)
+        }
+        Layout::Wide => {
+            let rows = pivot(periods, &rows, metadata.network(), columns)?;
+            // Without periods there are no farms, so only the fixed columns remain.
+            let template = Map::from_iter(
+                ["date", "period end", "network"].map(|column| (column.into(), Value::Null)),
+            );
+            write_rows_like(w, format, metadata, &template, &rows)
+        }
+    }
+}
+
+/// Pivot long farm rows into 1 row per period.
+fn pivot(
+    periods: &[Period],
+    rows: &[FarmRow],
+    network: Network,
+    columns: Columns,
+) -> io::Result<Vec<Map<String, Value>>> {
+    let mut farm_ids = rows.iter().map(|row| row.farm_id).collect::<Vec<_>>();
+    farm_ids.sort_unstable();
+    farm_ids.dedup();
+    let rows = rows
+        .iter()
+        .map(|row| ((row.date, row.farm_id), row))
+        .collect::<BTreeMap<_, _>>();
+
+    periods
+        .iter()
+        .map(|period| {
+            let date = period.start.date_naive();
+            let period_end = period.end.date_naive();
+            let mut map = Map::new();
+            map.insert("date".into(), date.to_string().into());
+            map.insert("period end".into(), period_end.to_string().into());
+            for &farm_id in &farm_ids {
+                let (node_count, resources) = rows
+                    .get(&(date, farm_id))
+                    .map(|row| (row.node_count, row.resources))
+                    .unwrap_or_default();
+                map.insert(format!("farm {farm_id} nodes"), node_count.into());
+                map.insert(format!("farm {farm_id} CRU"), resources.cru().into());
+                map.insert(format!("farm {farm_id} MRU"), resources.mru().into());
+                map.insert(format!("farm {farm_id} SRU"), resources.sru().into());
+                map.insert(format!("farm {farm_id} HRU"), resources.hru().into());
+                if columns.utilisation {
+                    let utilisation = rows
+                        .get(&(date, farm_id))
+                        .and_then(|row| row.utilisation.clone())
+                        .unwrap_or_else(|| {
+                            UtilisationColumns::new(&NodeTotals::default(), &Resources::default())
+                        });
+                    if let Value::Object(utilisation) = serde_json::to_value(utilisation)? {
+                        for (column, value) in utilisation {
+                            map.insert(format!("farm {farm_id} {column}"), value);
+                        }
+                    }
+                }
+            }
+            map.insert("network".into(), network.name().into());
+            Ok(map)
+        })
+        .collect()
+}
+
+/// Write per country or continent aggregates in the given format.
+pub fn write_region_aggregates(
+    w: &mut dyn io::Write,
+    format: Format,
+    metadata: &Metadata,
+    region: Region,
+    rows: &[RegionAggregate],
+) -> io::Result<()> {
+    let rows = rows
+        .iter()
+        .map(|row| RegionRow::new(row, metadata.network(), region))
+        .collect::<Vec<_>>();
+    let template = RegionRow::new(&RegionAggregate::default(), metadata.network(), region);
+    write_rows_like(w, format, metadata, &template, &rows)
+}
+
+/// Write raw nodes in the given format.
+pub fn write_nodes(
+    w: &mut dyn io::Write,
+    format: Format,
+    metadata: &Metadata,
+    nodes: &[Node],
+) -> io::Result<()> {
+    let rows = nodes.iter().map(NodeRow::from).collect::<Vec<_>>();
+    #[cfg(feature = "parquet")]
+    if format == Format::Parquet {
+        return parquet::write_nodes(w, &rows);
+    }
+    let template = NodeRow::from(&Node::new(0, 0, 0, Resources::default()));
+    write_rows_like(w, format, metadata, &template, &rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{aggregate, aggregate_by_farm};
+
+    fn utilisation(row: impl Serialize) -> Map<String, Value> {
+        to_object(&row)
+            .unwrap()
+            .into_iter()
+            .filter(|(column, _)| column.contains("used") || column.starts_with("free"))
+            .collect()
+    }
+
+    #[test]
+    fn utilisation_is_relative_to_nodes_reporting_usage() {
+        let nodes = [
+            Node::new(1, 1, 0, Resources::new(8, 0, 0, 0))
+                .with_resources_used(Some(Resources::new(2, 0, 0, 0))),
+            Node::new(2, 2, 0, Resources::new(8, 0, 0, 0)),
+        ];
+        let start = DateTime::from_timestamp(86400, 0).unwrap();
+        let periods = [Period {
+            start,
+            end: start + chrono::Days::new(1),
+        }];
+        let columns = Columns {
+            utilisation: true,
+            ..Default::default()
+        };
+
+        let rows = aggregate(&nodes, &periods, None);
+        let row = utilisation(AggregateRow::new(&rows[0], Network::Mainnet, columns));
+        assert_eq!(row["used CRU"], 2);
+        assert_eq!(row["free CRU"], 6);
+        assert_eq!(row["CRU used %"], 25.);
+        assert_eq!(row["MRU used %"], Value::Null);
+
+        let farms = aggregate_by_farm(&nodes, &periods);
+        let reporting = utilisation(FarmRow::new(&farms[0], Network::Mainnet, columns));
+        assert_eq!(reporting["CRU used %"], 25.);
+        let silent = utilisation(FarmRow::new(&farms[1], Network::Mainnet, columns));
+        assert_eq!(silent.len(), 12);
+        assert!(silent.values().all(Value::is_null), "{silent:?}");
+    }
+
+    #[test]
+    fn map_formats_reject_rows_without_location_before_writing() {
+        let metadata = Metadata::new(Network::Mainnet, String::new());
+        for format in [Format::GeoJson, Format::Kml] {
+            let mut buf = Vec::new();
+            let written = write_aggregates(&mut buf, format, &metadata, Columns::default(), &[]);
+            assert_eq!(written.unwrap_err().kind(), io::ErrorKind::Unsupported);
+            assert!(buf.is_empty(), "{format} wrote {} bytes", buf.len());
+            assert!(!format.supports_aggregates() && !format.supports_rows());
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use std::{io, sync::Arc};
+
+use ::parquet::arrow::ArrowWriter;
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, TimestampMillisecondArray,
+    UInt32Array, UInt64Array,
+};
+use arrow_schema::{Field, Schema};
+use chrono::{NaiveDate, NaiveTime};
+use serde_json::{Map, Value};
+
+use super::{AggregateRow, Columns, Metadata, NodeRow, RowWriter};
+use crate::Resources;
+
+/// Apache Parquet with typed columns. Only aggregates and nodes are supported, as generic rows
+/// don't carry type information.
+pub struct ParquetWriter;
+
+impl RowWriter for ParquetWriter {
+    fn write_rows(
+        &self,
+        _: &mut dyn io::Write,
+        _: &Metadata,
+        _: &[String],
+        _: &[Map<String, Value>],
+    ) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "parquet output is only supported for aggregates and nodes",
+        ))
+    }
+}
+
+/// Write aggregate rows as a parquet file, with the optional `columns` the rows were created with.
+pub fn write_aggregates(
+    w: &mut dyn io::Write,
+    rows: &[AggregateRow],
+    columns: Columns,
+) -> io::Result<()> {
+    let mut fields = vec![
+        column("date", timestamps(rows.iter().map(|r| date_millis(r.date)))),
+        column(
+            "period end",
+            timestamps(rows.iter().map(|r| date_millis(r.period_end))),
+        ),
+        column("node count", u64s(rows.iter().map(|r| r.node_count))),
+        column("farms with nodes", u64s(rows.iter().map(|r| r.farms))),
+    ];
+    fields.extend(resource_columns(
+        ["total CRU", "total MRU", "total SRU", "total HRU"],
+        rows.iter().map(|r| &r.resources),
+    ));
+    fields.push(column(
+        "network",
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| r.network.name()),
+        )),
+    ));
+
+    if columns.growth {
+        let growth = rows
+            .iter()
+            .filter_map(|r| r.growth.as_ref())
+            .collect::<Vec<_>>();
+        fields.push(column(
+            "new nodes",
+            u64s(growth.iter().map(|g| g.new_nodes)),
+        ));
+        fields.push(column(
+            "new farms",
+            u64s(growth.iter().map(|g| g.new_farms)),
+        ));
+        fields.extend(resource_columns(
+            ["new CRU", "new MRU", "new SRU", "new HRU"],
+            growth.iter().map(|g| &g.new_resources),
+        ));
+        fields.push(nullable_column(
+            "MoM growth %",
+            Arc::new(Float64Array::from_iter(growth.iter().map(|g| g.mom_growth))),
+        ));
+        fields.push(nullable_column(
+            "YoY growth %",
+            Arc::new(Float64Array::from_iter(growth.iter().map(|g| g.yoy_growth))),
+        ));
+    }
+    if columns.certification {
+        let certification = rows
+            .iter()
+            .filter_map(|r| r.certification.as_ref())
+            .collect::<Vec<_>>();
+        fields.push(column(
+            "diy nodes",
+            u64s(certification.iter().map(|c| c.diy_nodes)),
+        ));
+        fields.extend(resource_columns(
+            ["diy CRU", "diy MRU", "diy SRU", "diy HRU"],
+            certification.iter().map(|c| &c.diy_resources),
+        ));
+        fields.push(column(
+            "certified nodes",
+            u64s(certification.iter().map(|c| c.certified_nodes)),
+        ));
+        fields.extend(resource_columns(
+            [
+                "certified CRU",
+                "certified MRU",
+                "certified SRU",
+                "certified HRU",
+            ],
+            certification.iter().map(|c| &c.certified_resources),
+        ));
+    }
+    if columns.utilisation {
+        let utilisation = rows
+            .iter()
+            .filter_map(|r| r.utilisation.as_ref())
+            .collect::<Vec<_>>();
+        fields.extend(optional_resource_columns(
+            ["used CRU", "used MRU", "used SRU", "used HRU"],
+            utilisation.iter().map(|u| u.used),
+        ));
+        fields.extend(optional_resource_columns(
+            ["free CRU", "free MRU", "free SRU", "free HRU"],
+            utilisation.iter().map(|u| u.free),
+        ));
+        fields.push(nullable_column(
+            "CRU used %",
+            Arc::new(Float64Array::from_iter(utilisation.iter().map(|u| u.cru))),
+        ));
+        fields.push(nullable_column(
+            "MRU used %",
+            Arc::new(Float64Array::from_iter(utilisation.iter().map(|u| u.mru))),
+        ));
+        fields.push(nullable_column(
+            "SRU used %",
+            Arc::new(Float64Array::from_iter(utilisation.iter().map(|u| u.sru))),
+        ));
+        fields.push(nullable_column(
+            "HRU used %",
+            Arc::new(Float64Array::from_iter(utilisation.iter().map(|u| u.hru))),
+        ));
+    }
+    if columns.activity {
+        let activity = rows
+            .iter()
+            .filter_map(|r| r.activity.as_ref())
+            .collect::<Vec<_>>();
+        fields.push(column(
+            "active nodes",
+            u64s(activity.iter().map(|a| a.active_nodes)),
+        ));
+        fields.push(column(
+            "standby nodes",
+            u64s(activity.iter().map(|a| a.standby_nodes)),
+        ));
+        fields.push(column(
+            "down nodes",
+            u64s(activity.iter().map(|a| a.down_nodes)),
+        ));
+        fields.extend(resource_columns(
+            ["active CRU", "active MRU", "active SRU", "active HRU"],
+            activity.iter().map(|a| &a.active_resources),
+        ));
+    }
+
+    write_batch(w, fields)
+}
+
+/// Write node rows as a parquet file.
+pub fn write_nodes(w: &mut dyn io::Write, rows: &[NodeRow]) -> io::Result<()> {
+    let mut fields = vec![
+        column(
+            "node id",
+            Arc::new(UInt32Array::from_iter_values(
+                rows.iter().map(|r| r.node_id),
+            )),
+        ),
+        column(
+            "farm id",
+            Arc::new(UInt32Array::from_iter_values(
+                rows.iter().map(|r| r.farm_id),
+            )),
+        ),
+        column(
+            "created",
+            timestamps(rows.iter().map(|r| r.created.timestamp_millis())),
+        ),
+    ];
+    fields.extend(resource_columns(
+        ["CRU", "MRU", "SRU", "HRU"],
+        rows.iter().map(|r| &r.resources),
+    ));
+    fields.push(nullable_column(
+        "used CRU",
+        Arc::new(UInt64Array::from_iter(rows.iter().map(|r| r.used_cru))),
+    ));
+    fields.push(nullable_column(
+        "used MRU",
+        Arc::new(UInt64Array::from_iter(rows.iter().map(|r| r.used_mru))),
+    ));
+    fields.push(nullable_column(
+        "used SRU",
+        Arc::new(UInt64Array::from_iter(rows.iter().map(|r| r.used_sru))),
+    ));
+    fields.push(nullable_column(
+        "used HRU",
+        Arc::new(UInt64Array::from_iter(rows.iter().map(|r| r.used_hru))),
+    ));
+    fields.push(nullable_column(
+        "country",
+        Arc::new(StringArray::from_iter(
+            rows.iter().map(|r| r.country.as_deref()),
+        )),
+    ));
+    fields.push(nullable_column(
+        "city",
+        Arc::new(StringArray::from_iter(
+            rows.iter().map(|r| r.city.as_deref()),
+        )),
+    ));
+    fields.push(nullable_column(
+        "latitude",
+        Arc::new(Float64Array::from_iter(rows.iter().map(|r| r.latitude))),
+    ));
+    fields.push(nullable_column(
+        "longitude",
+        Arc::new(Float64Array::from_iter(rows.iter().map(|r| r.longitude))),
+    ));
+    fields.push(nullable_column(
+        "certification",
+        Arc::new(StringArray::from_iter(
+            rows.iter().map(|r| r.certification.map(|c| c.name())),
+        )),
+    ));
+    fields.push(nullable_column(
+        "farm certification",
+        Arc::new(StringArray::from_iter(
+            rows.iter().map(|r| r.farm_certification.map(|c| c.name())),
+        )),
+    ));
+    fields.push(nullable_column(
+        "dedicated farm",
+        Arc::new(BooleanArray::from_iter(
+            rows.iter().map(|r| r.dedicated_farm),
+        )),
+    ));
+    fields.push(nullable_column(
+        "updated at",
+        Arc::new(
+            TimestampMillisecondArray::from_iter(
+                rows.iter()
+                    .map(|r| r.updated_at.map(|u| u.timestamp_millis())),
+            )
+            .with_timezone("UTC"),
+        ),
+    ));
+    fields.push(nullable_column(
+        "status",
+        Arc::new(StringArray::from_iter(
+            rows.iter().map(|r| r.status.map(|s| s.name())),
+        )),
+    ));
+
+    write_batch(w, fields)
+}
+
+/// A named column, and whether it may contain nulls.
+type Column = (&'static str, ArrayRef, bool);
+
+fn column(name: &'static str, array: ArrayRef) -> Column {
+    (name, array, false)
+}
+
+fn nullable_column(name: &'static str, array: ArrayRef) -> Column {
+    (name, array, true)
+}
+
+fn u64s(values: impl Iterator<Item = u64>) -> ArrayRef {
+    Arc::new(UInt64Array::from_iter_values(values))
+}
+
+fn timestamps(millis: impl Iterator<Item = i64>) -> ArrayRef {
+    Arc::new(TimestampMillisecondArray::from_iter_values(millis).with_timezone("UTC"))
+}
+
+fn date_millis(date: NaiveDate) -> i64 {
+    date.and_time(NaiveTime::MIN).and_utc().timestamp_millis()
+}
+
+fn resource_columns<'a>(
+    names: [&'static str; 4],
+    resources: impl Iterator<Item = &'a Resources> + Clone,
+) -> [Column; 4] {
+    [
+        column(names[0], u64s(resources.clone().map(Resources::cru))),
+        column(names[1], u64s(resources.clone().map(Resources::mru))),
+        column(names[2], u64s(resources.clone().map(Resources::sru))),
+        column(names[3], u64s(resources.map(Resources::hru))),
+    ]
+}
+
+/// Like [`resource_columns`], with null columns for unknown resources.
+fn optional_resource_columns(
+    names: [&'static str; 4],
+    resources: impl Iterator<Item = Option<Resources>> + Clone,
+) -> [Column; 4] {
+    let column_of = |name, f: fn(&Resources) -> u64| {
+        let values = resources.clone().map(|r| r.as_ref().map(f));
+        nullable_column(name, Arc::new(UInt64Array::from_iter(values)) as ArrayRef)
+    };
+    [
+        column_of(names[0], Resources::cru),
+        column_of(names[1], Resources::mru),
+        column_of(names[2], Resources::sru),
+        column_of(names[3], Resources::hru),
+    ]
+}
+
+/// Write the columns as a single record batch.
+fn write_batch(w: &mut dyn io::Write, columns: Vec<Column>) -> io::Result<()> {
+    let schema = Arc::new(Schema::new(
+        columns
+            .iter()
+            .map(|(name, array, nullable)| Field::new(*name, array.data_type().clone(), *nullable))
+            .collect::<Vec<_>>(),
+    ));
+    let batch = RecordBatch::try_new(
+        schema.clone(),
+        columns.into_iter().map(|(_, array, _)| array).collect(),
+    )
+    .map_err(io::Error::other)?;
+
+    // The arrow writer requires a `Send` writer, so buffer the file first.
+    let mut buf = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buf, schema, None).map_err(io::Error::other)?;
+    writer.write(&batch).map_err(io::Error::other)?;
+    writer.close().map_err(io::Error::other)?;
+
+    w.write_all(&buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use arrow_array::RecordBatchReader;
+    use arrow_schema::SchemaRef;
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::Node;
+
+    fn read_back(buf: Vec<u8>) -> (SchemaRef, usize) {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(buf))
+            .unwrap()
+            .build()
+            .unwrap();
+        let schema = reader.schema();
+        let rows = reader.map(|batch| batch.unwrap().num_rows()).sum();
+        (schema, rows)
+    }
+
+    #[test]
+    fn aggregates_without_rows_keep_the_optional_columns() {
+        let columns = Columns {
+            growth: true,
+            certification: true,
+            utilisation: true,
+            activity: true,
+        };
+        let mut buf = Vec::new();
+        write_aggregates(&mut buf, &[], columns).unwrap();
+
+        let (schema, rows) = read_back(buf);
+        assert_eq!(rows, 0);
+        for name in ["new nodes", "diy nodes", "used CRU", "active nodes"] {
+            assert!(schema.field_with_name(name).is_ok(), "missing {name}");
+        }
+        assert!(!schema.field_with_name("node count").unwrap().is_nullable());
+        assert!(schema
+            .field_with_name("MoM growth %")
+            .unwrap()
+            .is_nullable());
+        assert!(schema.field_with_name("free HRU").unwrap().is_nullable());
+    }
+
+    #[test]
+    fn nullability_does_not_depend_on_the_rows() {
+        let node = Node::new(1, 2, 0, Resources::new(8, 16, 32, 64))
+            .with_resources_used(Some(Resources::new(1, 2, 3, 4)));
+        let mut buf = Vec::new();
+        write_nodes(&mut buf, &[NodeRow::from(&node)]).unwrap();
+
+        let (schema, rows) = read_back(buf);
+        assert_eq!(rows, 1);
+        assert!(!schema.field_with_name("node id").unwrap().is_nullable());
+        assert!(schema.field_with_name("used CRU").unwrap().is_nullable());
+        assert!(schema.field_with_name("country").unwrap().is_nullable());
+    }
+}
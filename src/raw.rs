@@ -0,0 +1,199 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Utc};
+use flate2::{bufread::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+use crate::{source, Network, Node, NodeCounterError, Source};
+
+/// Magic bytes at the start of a gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The response bodies of a fetch exactly as returned by the endpoint, used for reproducible and
+/// offline runs. The nodes are decoded from the responses, the same way as when fetching.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawFile")]
+pub struct RawFetch {
+    fetched_at: DateTime<Utc>,
+    network: Network,
+    source: Source,
+    endpoint: String,
+    responses: Vec<RawResponse>,
+    #[serde(skip)]
+    nodes: Vec<Node>,
+}
+
+/// A [`RawFetch`] as stored, before its nodes are decoded.
+#[derive(Deserialize)]
+struct RawFile {
+    fetched_at: DateTime<Utc>,
+    network: Network,
+    source: Source,
+    endpoint: String,
+    responses: Vec<RawResponse>,
+}
+
+impl TryFrom<RawFile> for RawFetch {
+    type Error = NodeCounterError;
+
+    fn try_from(file: RawFile) -> Result<Self, Self::Error> {
+        let nodes = source::decode_responses(file.source, &file.endpoint, &file.responses)?;
+        Ok(Self {
+            fetched_at: file.fetched_at,
+            network: file.network,
+            source: file.source,
+            endpoint: file.endpoint,
+            responses: file.responses,
+            nodes,
+        })
+    }
+}
+
+/// The body of a single response, with the operation it answered, like `ListNodes` for graphql
+/// or `nodes` for the Grid Proxy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawResponse {
+    operation: String,
+    body: Box<RawValue>,
+}
+
+impl RawResponse {
+    /// The operation the response answered.
+    pub fn operation(&self) -> &str {
+        &self.operation
+    }
+
+    /// The JSON body, exactly as returned by the endpoint.
+    pub fn body(&self) -> &str {
+        self.body.get()
+    }
+}
+
+/// Collects the response bodies of the pages of a fetch, in the order they were received.
+#[derive(Default)]
+pub(crate) struct Recorder(Mutex<Vec<RawResponse>>);
+
+impl Recorder {
+    /// Keep the body of a response to `operation`. The body must be valid JSON.
+    pub fn record(&self, operation: &str, body: &[u8]) -> Result<(), serde_json::Error> {
+        let body = serde_json::from_slice::<&RawValue>(body)?.to_owned();
+        self.0.lock().unwrap().push(RawResponse {
+            operation: operation.to_string(),
+            body,
+        });
+        Ok(())
+    }
+
+    pub fn into_responses(self) -> Vec<RawResponse> {
+        self.0.into_inner().unwrap()
+    }
+}
+
+impl RawFetch {
+    pub(crate) fn new(
+        fetched_at: DateTime<Utc>,
+        network: Network,
+        source: Source,
+        endpoint: impl Into<String>,
+        responses: Vec<RawResponse>,
+        nodes: Vec<Node>,
+    ) -> Self {
+        Self {
+            fetched_at,
+            network,
+            source,
+            endpoint: endpoint.into(),
+            responses,
+            nodes,
+        }
+    }
+
+    /// Time at which the nodes were fetched.
+    pub fn fetched_at(&self) -> DateTime<Utc> {
+        self.fetched_at
+    }
+
+    /// Network the nodes were fetched from.
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Source the nodes were fetched from.
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    /// Endpoint the nodes were fetched from.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// The response bodies, in the order they were received.
+    pub fn responses(&self) -> &[RawResponse] {
+        &self.responses
+    }
+
+    /// The nodes decoded from the responses.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Consume the fetch, returning the nodes.
+    pub fn into_nodes(self) -> Vec<Node> {
+        self.nodes
+    }
+
+    /// Save as JSON to the given path. If the path ends in `.gz`, the file is gzip compressed.
+    pub fn save(&self, path: &Path) -> Result<(), NodeCounterError> {
+        let io_err = |source| NodeCounterError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        let file = BufWriter::new(File::create(path).map_err(io_err)?);
+        let result = if path.extension().is_some_and(|ext| ext == "gz") {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            serde_json::to_writer(&mut encoder, self)
+                .map_err(io::Error::from)
+                .and_then(|_| encoder.finish())
+                .and_then(|mut file| file.flush())
+        } else {
+            let mut file = file;
+            serde_json::to_writer(&mut file, self)
+                .map_err(io::Error::from)
+                .and_then(|_| file.flush())
+        };
+
+        result.map_err(io_err)
+    }
+
+    /// Load from the given path. Gzip compressed files are detected automatically.
+    pub fn load(path: &Path) -> Result<Self, NodeCounterError> {
+        serde_json::from_reader(open(path)?).map_err(|source| NodeCounterError::Decode {
+            endpoint: path.display().to_string(),
+            source,
+        })
+    }
+}
+
+/// Open a file for reading, transparently decompressing it if it is gzip compressed.
+pub(crate) fn open(path: &Path) -> Result<Box<dyn Read>, NodeCounterError> {
+    let io_err = |source| NodeCounterError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let mut reader = BufReader::new(File::open(path).map_err(io_err)?);
+    let is_gzip = reader.fill_buf().map_err(io_err)?.starts_with(&GZIP_MAGIC);
+    Ok(if is_gzip {
+        Box::new(BufReader::new(GzDecoder::new(reader)))
+    } else {
+        Box::new(reader)
+    })
+}
@@ -0,0 +1,149 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use reqwest::{header::RETRY_AFTER, Response};
+
+use crate::NodeCounterError;
+
+/// Upper bound for a server provided `Retry-After`, so a misbehaving server can't stall a run.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(600);
+
+/// How failed requests are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Amount of retries after the first attempt, per endpoint.
+    pub max_retries: u32,
+    /// Backoff before the first retry. Every following retry doubles it.
+    pub initial_backoff: Duration,
+    /// Maximum backoff between 2 attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy which never retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// The backoff before retry number `retry` (starting from 0). The exponential backoff is
+    /// jittered to a random value between half and the full backoff, so concurrent clients don't
+    /// retry in lockstep.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        let half = backoff / 2;
+        half + half.mul_f64(jitter())
+    }
+}
+
+/// Whether a failed request is worth retrying: network errors, server errors and rate limits.
+pub(crate) fn is_retryable(err: &NodeCounterError) -> bool {
+    match err {
+        NodeCounterError::Network { .. } => true,
+        NodeCounterError::HttpStatus { status, .. } => {
+            status.is_server_error() || status.as_u16() == 429
+        }
+        _ => false,
+    }
+}
+
+/// Parse the `Retry-After` header of a response, either as delay in seconds or as HTTP date.
+pub(crate) fn retry_after(response: &Response) -> Option<Duration> {
+    parse_retry_after(
+        response.headers().get(RETRY_AFTER)?.to_str().ok()?,
+        Utc::now(),
+    )
+}
+
+/// Parse a `Retry-After` value received at `now`. Dates in the past mean no delay.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let at = DateTime::parse_from_rfc2822(value)
+                .ok()?
+                .with_timezone(&Utc);
+            (at - now).to_std().unwrap_or_default()
+        }
+    };
+    Some(delay.min(MAX_RETRY_AFTER))
+}
+
+/// A random value in [0, 1), seeded by the randomly keyed std hasher to avoid a dependency.
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_is_jittered_and_capped() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(10),
+        };
+        for (retry, full) in [(0, 1), (1, 2), (3, 8), (4, 10), (u32::MAX, 10)] {
+            let full = Duration::from_secs(full);
+            for _ in 0..100 {
+                let backoff = policy.backoff(retry);
+                assert!(
+                    backoff >= full / 2 && backoff <= full,
+                    "retry {retry}: {backoff:?} not within half and {full:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_dates() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+        let parse = |value| parse_retry_after(value, now);
+
+        assert_eq!(parse("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse(" 0 "), Some(Duration::ZERO));
+        assert_eq!(
+            parse("Wed, 21 Oct 2015 07:30:00 GMT"),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(parse("Wed, 21 Oct 2015 07:00:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse("86400"), Some(MAX_RETRY_AFTER));
+        assert_eq!(
+            parse("Thu, 22 Oct 2015 07:28:00 GMT"),
+            Some(MAX_RETRY_AFTER)
+        );
+        assert_eq!(parse("soon"), None);
+        assert_eq!(parse("-5"), None);
+    }
+}
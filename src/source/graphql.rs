@@ -0,0 +1,346 @@
+use serde::{
+    de::{DeserializeOwned, Error as _},
+    Deserialize, Serialize,
+};
+
+use super::{http::Http, with_farms, Farm, NodeSource};
+use crate::{
+    raw::{RawResponse, Recorder},
+    types::de_known,
+    FarmCertification, GraphQLRequest, GraphQLResponse, Node, NodeCountReply, NodeCounterError,
+    NodeReply, PageVariables,
+};
+
+const NODE_QUERY: &str = r#"
+query ListNodes($limit: Int!, $offset: Int!) {  nodes(limit: $limit, offset: $offset, orderBy: nodeID_ASC) {    nodeID    created    farmID    resourcesTotal {      cru      hru      mru      sru    }    resourcesUsed {      cru      hru      mru      sru    }    country    city    location {      latitude      longitude    }    certification    updatedAt  }}
+"#;
+
+const NODE_COUNT_QUERY: &str = r#"
+query CountNodes {  nodesConnection(orderBy: nodeID_ASC) {    totalCount  }}
+"#;
+
+const FARM_QUERY: &str = r#"
+query ListFarms($limit: Int!, $offset: Int!) {  farms(limit: $limit, offset: $offset, orderBy: farmID_ASC) {    farmID    certification    dedicatedFarm  }}
+"#;
+
+#[derive(Deserialize)]
+struct FarmReply {
+    farms: Vec<GraphQLFarm>,
+}
+
+#[derive(Deserialize)]
+struct GraphQLFarm {
+    #[serde(rename = "farmID")]
+    farm_id: u32,
+    #[serde(default, deserialize_with = "de_known")]
+    certification: Option<FarmCertification>,
+    #[serde(rename = "dedicatedFarm", default)]
+    dedicated_farm: Option<bool>,
+}
+
+impl From<GraphQLFarm> for Farm {
+    fn from(farm: GraphQLFarm) -> Self {
+        Farm {
+            farm_id: farm.farm_id,
+            certification: farm.certification,
+            dedicated: farm.dedicated_farm,
+        }
+    }
+}
+
+/// Fetches nodes from the graphql indexer.
+pub(crate) struct GraphQLSource<'a> {
+    http: Http<'a>,
+    page_size: u32,
+    recorder: Option<&'a Recorder>,
+}
+
+impl<'a> GraphQLSource<'a> {
+    pub fn new(http: Http<'a>, page_size: u32) -> Self {
+        Self {
+            http,
+            page_size,
+            recorder: None,
+        }
+    }
+
+    /// Keep the response bodies of all listed pages in the recorder, if any.
+    pub fn recording(mut self, recorder: Option<&'a Recorder>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+
+    /// Execute a graphql query.
+    async fn query<V: Serialize, T: DeserializeOwned>(
+        &self,
+        operation_name: &str,
+        query: &str,
+        variables: Option<V>,
+    ) -> Result<T, NodeCounterError> {
+        let body = self.send(operation_name, query, variables).await?;
+        decode(self.http.endpoint(), &body)
+    }
+
+    /// Send a graphql query and read the body of the response.
+    async fn send<V: Serialize>(
+        &self,
+        operation_name: &str,
+        query: &str,
+        variables: Option<V>,
+    ) -> Result<Vec<u8>, NodeCounterError> {
+        let request = GraphQLRequest {
+            operation_name,
+            query,
+            variables,
+        };
+        let reply = self
+            .http
+            .send(operation_name, |client, endpoint| {
+                client.post(endpoint).json(&request)
+            })
+            .await?;
+
+        Ok(reply.body)
+    }
+
+    /// Request all pages of a paginated query, until an empty page is returned. The indexer may
+    /// cap the page size, so a short page doesn't mark the end.
+    async fn all<R: DeserializeOwned, T>(
+        &self,
+        operation_name: &str,
+        query: &str,
+        items: impl Fn(R) -> Vec<T>,
+    ) -> Result<Vec<T>, NodeCounterError> {
+        let mut all = Vec::new();
+        loop {
+            let body = self
+                .send(
+                    operation_name,
+                    query,
+                    Some(PageVariables {
+                        limit: self.page_size,
+                        offset: all.len() as u32,
+                    }),
+                )
+                .await?;
+            if let Some(recorder) = self.recorder {
+                recorder.record(operation_name, &body).map_err(|source| {
+                    NodeCounterError::Decode {
+                        endpoint: self.http.endpoint().to_string(),
+                        source,
+                    }
+                })?;
+            }
+
+            let page = items(decode(self.http.endpoint(), &body)?);
+            if page.is_empty() {
+                return Ok(all);
+            }
+            all.extend(page);
+        }
+    }
+
+    /// Fetch all farms, one page at a time.
+    async fn fetch_farms(&self) -> Result<Vec<Farm>, NodeCounterError> {
+        let farms = self
+            .all("ListFarms", FARM_QUERY, |reply: FarmReply| reply.farms)
+            .await?;
+        Ok(farms.into_iter().map(Farm::from).collect())
+    }
+}
+
+/// Decode the body of a graphql response.
+fn decode<T: DeserializeOwned>(endpoint: &str, body: &[u8]) -> Result<T, NodeCounterError> {
+    serde_json::from_slice::<GraphQLResponse<T>>(body)
+        .map_err(|source| NodeCounterError::Decode {
+            endpoint: endpoint.to_string(),
+            source,
+        })?
+        .into_result()
+}
+
+/// Decode the nodes from the recorded pages of a fetch, the same way as when fetching them.
+pub(crate) fn decode_responses(
+    endpoint: &str,
+    responses: &[RawResponse],
+) -> Result<Vec<Node>, NodeCounterError> {
+    let mut nodes = Vec::new();
+    let mut farms = Vec::new();
+    for response in responses {
+        let body = response.body().as_bytes();
+        match response.operation() {
+            "ListNodes" => nodes.extend(decode::<NodeReply>(endpoint, body)?.into_nodes()),
+            "ListFarms" => farms.extend(
+                decode::<FarmReply>(endpoint, body)?
+                    .farms
+                    .into_iter()
+                    .map(Farm::from),
+            ),
+            operation => {
+                return Err(NodeCounterError::Decode {
+                    endpoint: endpoint.to_string(),
+                    source: serde_json::Error::custom(format!(
+                        "unexpected operation \"{operation}\""
+                    )),
+                })
+            }
+        }
+    }
+    Ok(with_farms(nodes, farms))
+}
+
+impl NodeSource for GraphQLSource<'_> {
+    fn endpoint(&self) -> &str {
+        self.http.endpoint()
+    }
+
+    async fn fetch_node_count(&self) -> Result<u64, NodeCounterError> {
+        Ok(self
+            .query::<(), NodeCountReply>("CountNodes", NODE_COUNT_QUERY, None)
+            .await?
+            .total_count())
+    }
+
+    async fn fetch_nodes(&self) -> Result<Vec<Node>, NodeCounterError> {
+        let nodes = self
+            .all("ListNodes", NODE_QUERY, NodeReply::into_nodes)
+            .await?;
+        Ok(with_farms(nodes, self.fetch_farms().await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{fetch_checked, source::mock, RetryPolicy};
+
+    /// Serve `total` nodes on 2 farms, at most 2 per page, while counting `count` nodes.
+    async fn indexer(total: u32, count: u32, pages: Arc<AtomicUsize>) -> String {
+        mock::serve(move |_, body| {
+            let request = serde_json::from_str::<Value>(body).unwrap();
+            let offset = request["variables"]["offset"].as_u64().unwrap_or(0) as u32;
+            let limit = request["variables"]["limit"].as_u64().unwrap_or(0) as u32;
+            let ids = offset + 1..=(offset + limit.min(2)).min(total);
+            let data = match request["operation_name"].as_str().unwrap() {
+                "CountNodes" => json!({ "nodesConnection": { "totalCount": count } }),
+                "ListNodes" => {
+                    pages.fetch_add(1, Ordering::Relaxed);
+                    let nodes = ids
+                        .map(|id| {
+                            json!({
+                                "nodeID": id,
+                                "farmID": id % 2 + 1,
+                                "created": 1_600_000_000,
+                                "resourcesTotal": { "cru": "4", "mru": "0", "sru": "0", "hru": "0" },
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    json!({ "nodes": nodes })
+                }
+                "ListFarms" => {
+                    let farms = ids
+                        .take_while(|&id| id <= 2)
+                        .map(|id| json!({ "farmID": id, "certification": "Gold" }))
+                        .collect::<Vec<_>>();
+                    json!({ "farms": farms })
+                }
+                operation => panic!("unexpected operation {operation}"),
+            };
+            (Vec::new(), json!({ "data": data }).to_string())
+        })
+        .await
+    }
+
+    fn response(operation: &str, body: &str) -> RawResponse {
+        serde_json::from_str(&format!(
+            r#"{{"operation": "{operation}", "body": {body}}}"#
+        ))
+        .unwrap()
+    }
+
+    async fn fetch(endpoint: &str) -> Result<Vec<Node>, NodeCounterError> {
+        let client = reqwest::Client::new();
+        let active = AtomicUsize::new(0);
+        let http = Http::new(&client, RetryPolicy::none(), vec![endpoint], &active);
+        fetch_checked(&GraphQLSource::new(http, 1000)).await
+    }
+
+    #[tokio::test]
+    async fn pages_until_an_empty_page() {
+        let pages = Arc::new(AtomicUsize::new(0));
+        let endpoint = indexer(5, 5, pages.clone()).await;
+
+        let nodes = fetch(&endpoint).await.unwrap();
+        assert_eq!(
+            nodes.iter().map(Node::node_id).collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5]
+        );
+        // The capped pages of 2, 2 and 1 nodes, and the empty page.
+        assert_eq!(pages.load(Ordering::Relaxed), 4);
+        assert!(nodes
+            .iter()
+            .all(|node| node.farm_certification() == Some(FarmCertification::Gold)));
+    }
+
+    #[tokio::test]
+    async fn fewer_nodes_than_counted_is_incomplete() {
+        let endpoint = indexer(5, 6, Arc::default()).await;
+
+        match fetch(&endpoint).await {
+            Err(NodeCounterError::IncompleteFetch { expected, fetched }) => {
+                assert_eq!((expected, fetched), (6, 5));
+            }
+            other => panic!("expected an incomplete fetch, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn decodes_recorded_pages() {
+        let responses = [
+            (
+                "ListNodes",
+                r#"{"data": {"nodes": [{"nodeID": 1, "farmID": 7, "created": 0, "resourcesTotal": {"cru": "2", "mru": "0", "sru": "0", "hru": "0"}, "twinID": 3}]}}"#,
+            ),
+            ("ListNodes", r#"{"data": {"nodes": []}}"#),
+            (
+                "ListFarms",
+                r#"{"data": {"farms": [{"farmID": 7, "certification": "NotCertified", "dedicatedFarm": true}]}}"#,
+            ),
+        ]
+        .map(|(operation, body)| response(operation, body));
+
+        let nodes = decode_responses("test", &responses).unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].resources_total().cru(), 2);
+        assert_eq!(
+            nodes[0].farm_certification(),
+            Some(FarmCertification::NotCertified)
+        );
+        assert_eq!(nodes[0].dedicated_farm(), Some(true));
+    }
+
+    #[test]
+    fn errors_in_recorded_pages_are_reported() {
+        let responses = [response(
+            "ListNodes",
+            r#"{"data": null, "errors": [{"message": "boom"}]}"#,
+        )];
+        assert!(matches!(
+            decode_responses("test", &responses),
+            Err(NodeCounterError::GraphQL { partial: false, .. })
+        ));
+
+        let responses = [response("Unknown", "{}")];
+        assert!(matches!(
+            decode_responses("test", &responses),
+            Err(NodeCounterError::Decode { .. })
+        ));
+    }
+}
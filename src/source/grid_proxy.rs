@@ -0,0 +1,294 @@
+use serde::{
+    de::{DeserializeOwned, Error as _},
+    Deserialize,
+};
+
+use super::{http::Http, with_farms, Farm, NodeSource};
+use crate::{
+    raw::{RawResponse, Recorder},
+    types::{de_known, de_location, de_timestamp},
+    FarmCertification, Location, Node, NodeCertification, NodeCounterError, NodeStatus, Resources,
+};
+
+/// Header holding the total amount of nodes when requested with `ret_count`.
+const COUNT_HEADER: &str = "count";
+
+/// Fetches nodes from the Grid Proxy REST API.
+pub(crate) struct GridProxySource<'a> {
+    http: Http<'a>,
+    page_size: u32,
+    recorder: Option<&'a Recorder>,
+}
+
+/// A node as returned by the Grid Proxy `/nodes` endpoint.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProxyNode {
+    node_id: u32,
+    farm_id: u32,
+    created: i64,
+    #[serde(rename = "total_resources")]
+    total_resources: Resources,
+    #[serde(rename = "used_resources", default)]
+    used_resources: Option<Resources>,
+    country: Option<String>,
+    city: Option<String>,
+    #[serde(default, deserialize_with = "de_location")]
+    location: Option<Location>,
+    #[serde(rename = "certificationType", default, deserialize_with = "de_known")]
+    certification: Option<NodeCertification>,
+    #[serde(default, deserialize_with = "de_timestamp")]
+    updated_at: Option<i64>,
+    #[serde(default, deserialize_with = "de_known")]
+    status: Option<NodeStatus>,
+}
+
+/// A farm as returned by the Grid Proxy `/farms` endpoint.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProxyFarm {
+    farm_id: u32,
+    #[serde(rename = "certificationType", default, deserialize_with = "de_known")]
+    certification: Option<FarmCertification>,
+    #[serde(default)]
+    dedicated: Option<bool>,
+}
+
+impl From<ProxyNode> for Node {
+    fn from(node: ProxyNode) -> Self {
+        Node::new(
+            node.node_id,
+            node.farm_id,
+            node.created,
+            node.total_resources,
+        )
+        .with_location(node.country, node.city, node.location)
+        .with_resources_used(node.used_resources)
+        .with_certification(node.certification)
+        .with_activity(node.updated_at, node.status)
+    }
+}
+
+impl From<ProxyFarm> for Farm {
+    fn from(farm: ProxyFarm) -> Self {
+        Farm {
+            farm_id: farm.farm_id,
+            certification: farm.certification,
+            dedicated: farm.dedicated,
+        }
+    }
+}
+
+impl<'a> GridProxySource<'a> {
+    pub fn new(http: Http<'a>, page_size: u32) -> Self {
+        Self {
+            http,
+            page_size,
+            recorder: None,
+        }
+    }
+
+    /// Keep the response bodies of all listed pages in the recorder, if any.
+    pub fn recording(mut self, recorder: Option<&'a Recorder>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+
+    /// Request a page of a listing like `nodes`, starting from 1, sorted by `sort_by`. If `count`
+    /// is set, the total amount of items is requested as well, and the page is not recorded.
+    async fn page<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        sort_by: &str,
+        page: u32,
+        size: u32,
+        count: bool,
+    ) -> Result<Reply<T>, NodeCounterError> {
+        let reply = self
+            .http
+            .send(&format!("GET /{path} page {page}"), |client, endpoint| {
+                client
+                    .get(format!("{}/{path}", endpoint.trim_end_matches('/')))
+                    .query(&[
+                        ("page", page.to_string()),
+                        ("size", size.to_string()),
+                        ("sort_by", sort_by.to_string()),
+                        ("sort_order", "asc".to_string()),
+                        ("ret_count", count.to_string()),
+                    ])
+            })
+            .await?;
+
+        let decode_err = |source| NodeCounterError::Decode {
+            endpoint: self.http.endpoint().to_string(),
+            source,
+        };
+        let total = reply
+            .headers
+            .get(COUNT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                value.trim().parse().map_err(|_| {
+                    decode_err(serde_json::Error::custom(format!(
+                        "invalid node count header \"{value}\""
+                    )))
+                })
+            })
+            .transpose()?;
+        if let (Some(recorder), false) = (self.recorder, count) {
+            recorder.record(path, &reply.body).map_err(decode_err)?;
+        }
+        let items = serde_json::from_slice(&reply.body).map_err(decode_err)?;
+
+        Ok(Reply { total, items })
+    }
+
+    /// Request all pages of a listing, until an empty page is returned. The proxy may cap the
+    /// page size, so a short page doesn't mark the end.
+    async fn all<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        sort_by: &str,
+    ) -> Result<Vec<T>, NodeCounterError> {
+        let mut items = Vec::new();
+        for page in 1.. {
+            let page = self
+                .page(path, sort_by, page, self.page_size, false)
+                .await?
+                .items;
+            if page.is_empty() {
+                break;
+            }
+            items.extend(page);
+        }
+        Ok(items)
+    }
+}
+
+/// A page of a listing.
+struct Reply<T> {
+    total: Option<u64>,
+    items: Vec<T>,
+}
+
+impl NodeSource for GridProxySource<'_> {
+    fn endpoint(&self) -> &str {
+        self.http.endpoint()
+    }
+
+    async fn fetch_node_count(&self) -> Result<u64, NodeCounterError> {
+        self.page::<ProxyNode>("nodes", "node_id", 1, 1, true)
+            .await?
+            .total
+            .ok_or_else(|| NodeCounterError::Decode {
+                endpoint: self.http.endpoint().to_string(),
+                source: serde_json::Error::custom("response has no node count header"),
+            })
+    }
+
+    async fn fetch_nodes(&self) -> Result<Vec<Node>, NodeCounterError> {
+        let nodes = self.all::<ProxyNode>("nodes", "node_id").await?;
+        let farms = self.all::<ProxyFarm>("farms", "farm_id").await?;
+        Ok(with_farms(
+            nodes.into_iter().map(Node::from).collect(),
+            farms.into_iter().map(Farm::from).collect(),
+        ))
+    }
+}
+
+/// Decode the nodes from the recorded pages of a fetch, the same way as when fetching them.
+pub(crate) fn decode_responses(
+    endpoint: &str,
+    responses: &[RawResponse],
+) -> Result<Vec<Node>, NodeCounterError> {
+    let decode_err = |source| NodeCounterError::Decode {
+        endpoint: endpoint.to_string(),
+        source,
+    };
+
+    let mut nodes = Vec::new();
+    let mut farms = Vec::new();
+    for response in responses {
+        let body = response.body();
+        match response.operation() {
+            "nodes" => nodes.extend(
+                serde_json::from_str::<Vec<ProxyNode>>(body)
+                    .map_err(decode_err)?
+                    .into_iter()
+                    .map(Node::from),
+            ),
+            "farms" => farms.extend(
+                serde_json::from_str::<Vec<ProxyFarm>>(body)
+                    .map_err(decode_err)?
+                    .into_iter()
+                    .map(Farm::from),
+            ),
+            operation => {
+                return Err(decode_err(serde_json::Error::custom(format!(
+                    "unexpected operation \"{operation}\""
+                ))))
+            }
+        }
+    }
+    Ok(with_farms(nodes, farms))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{fetch_checked, source::mock, RetryPolicy};
+
+    #[tokio::test]
+    async fn pages_until_an_empty_page() {
+        // Serve 5 nodes on 2 farms, at most 2 per page.
+        let endpoint = mock::serve(|target, _| {
+            let param = |name| mock::query_param(target, name).unwrap();
+            let page = param("page").parse::<u32>().unwrap();
+            let size = param("size").parse::<u32>().unwrap().min(2);
+            let ids = (page - 1) * size + 1..=page * size;
+            let headers = match param("ret_count") {
+                "true" => vec![("count", "5".to_string())],
+                _ => Vec::new(),
+            };
+            let items = if target.starts_with("/nodes") {
+                ids.take_while(|&id| id <= 5)
+                    .map(|id| {
+                        json!({
+                            "nodeId": id,
+                            "farmId": id % 2 + 1,
+                            "created": 1_600_000_000,
+                            "total_resources": { "cru": 4, "mru": 0, "sru": 0, "hru": 0 },
+                            "status": "up",
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            } else {
+                ids.take_while(|&id| id <= 2)
+                    .map(|id| json!({ "farmId": id, "certificationType": "Gold" }))
+                    .collect()
+            };
+            (headers, json!(items).to_string())
+        })
+        .await;
+
+        let client = reqwest::Client::new();
+        let active = AtomicUsize::new(0);
+        let http = Http::new(&client, RetryPolicy::none(), vec![&endpoint], &active);
+        let nodes = fetch_checked(&GridProxySource::new(http, 1000))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            nodes.iter().map(Node::node_id).collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5]
+        );
+        assert!(nodes
+            .iter()
+            .all(|node| node.status() == Some(NodeStatus::Up)
+                && node.farm_certification() == Some(FarmCertification::Gold)));
+    }
+}
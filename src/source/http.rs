@@ -0,0 +1,144 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use reqwest::{header::HeaderMap, RequestBuilder};
+
+use crate::{
+    retry::{self, RetryPolicy},
+    NodeCounterError,
+};
+
+/// Sends requests to an ordered list of endpoints, retrying on transient failures and failing
+/// over to the next endpoint once the retries for an endpoint are exhausted.
+pub(crate) struct Http<'a> {
+    client: &'a reqwest::Client,
+    retry: RetryPolicy,
+    endpoints: Vec<&'a str>,
+    /// Index in `endpoints` of the endpoint currently in use. Once an endpoint fails, the next
+    /// one is used for all following requests.
+    active: &'a AtomicUsize,
+}
+
+/// A successful response.
+pub(crate) struct Reply {
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl<'a> Http<'a> {
+    /// Create a new `Http`. `endpoints` must not be empty.
+    pub fn new(
+        client: &'a reqwest::Client,
+        retry: RetryPolicy,
+        endpoints: Vec<&'a str>,
+        active: &'a AtomicUsize,
+    ) -> Self {
+        Self {
+            client,
+            retry,
+            endpoints,
+            active,
+        }
+    }
+
+    /// The endpoint currently in use.
+    pub fn endpoint(&self) -> &'a str {
+        self.endpoints[self
+            .active
+            .load(Ordering::Relaxed)
+            .min(self.endpoints.len() - 1)]
+    }
+
+    /// Send a request, built by `build` for an endpoint, and read its body.
+    pub async fn send(
+        &self,
+        operation: &str,
+        build: impl Fn(&reqwest::Client, &str) -> RequestBuilder,
+    ) -> Result<Reply, NodeCounterError> {
+        let attempts = self.retry.max_retries + 1;
+
+        let mut idx = self.active.load(Ordering::Relaxed);
+        loop {
+            let endpoint = self.endpoints[idx];
+            let mut attempt = 1;
+            let err = loop {
+                log::info!("{operation}: attempt {attempt}/{attempts} on {endpoint}");
+                let err = match self.send_once(endpoint, &build).await {
+                    Ok(reply) => return Ok(reply),
+                    Err(e) if !retry::is_retryable(&e) => return Err(e),
+                    Err(e) => e,
+                };
+                if attempt == attempts {
+                    break err;
+                }
+
+                let backoff = match &err {
+                    NodeCounterError::HttpStatus {
+                        retry_after: Some(retry_after),
+                        ..
+                    } => *retry_after,
+                    _ => self.retry.backoff(attempt - 1),
+                };
+                log::warn!(
+                    "{operation}: attempt {attempt}/{attempts} on {endpoint} failed, retrying in {backoff:.1?}: {err}"
+                );
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            };
+
+            if idx + 1 == self.endpoints.len() {
+                log::warn!("{operation}: all endpoints failed: {err}");
+                return Err(err);
+            }
+            idx += 1;
+            log::warn!(
+                "{operation}: giving up on {endpoint}, failing over to {}: {err}",
+                self.endpoints[idx]
+            );
+            self.active.store(idx, Ordering::Relaxed);
+        }
+    }
+
+    /// Send a single request to an endpoint.
+    async fn send_once(
+        &self,
+        endpoint: &str,
+        build: impl Fn(&reqwest::Client, &str) -> RequestBuilder,
+    ) -> Result<Reply, NodeCounterError> {
+        let network_err = |source| NodeCounterError::Network {
+            endpoint: endpoint.to_string(),
+            source,
+        };
+
+        let response = build(self.client, endpoint)
+            .send()
+            .await
+            .map_err(network_err)?;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let retry_after = retry::retry_after(&response);
+        let body = response.bytes().await.map_err(network_err)?;
+        if !status.is_success() {
+            return Err(NodeCounterError::HttpStatus {
+                endpoint: endpoint.to_string(),
+                status,
+                body: truncate_body(&body),
+                retry_after,
+            });
+        }
+
+        Ok(Reply {
+            headers,
+            body: body.to_vec(),
+        })
+    }
+}
+
+/// Maximum amount of bytes of a response body kept in an error.
+const MAX_ERROR_BODY: usize = 512;
+
+/// Lossy convert a response body to a string for error reporting, truncating long bodies.
+fn truncate_body(body: &[u8]) -> String {
+    let body = String::from_utf8_lossy(&body[..body.len().min(MAX_ERROR_BODY)]);
+    body.trim().to_string()
+}
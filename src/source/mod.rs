@@ -0,0 +1,96 @@
+use std::{collections::HashMap, future::Future};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{raw::RawResponse, FarmCertification, Network, Node, NodeCounterError};
+
+mod graphql;
+mod grid_proxy;
+mod http;
+#[cfg(test)]
+mod mock;
+
+pub(crate) use graphql::GraphQLSource;
+pub(crate) use grid_proxy::GridProxySource;
+pub(crate) use http::Http;
+
+/// A backend nodes can be fetched from. All sources normalise their nodes into the same [`Node`]
+/// model, so the result can be aggregated and written regardless of the source.
+pub trait NodeSource {
+    /// The endpoint currently queried.
+    fn endpoint(&self) -> &str;
+
+    /// Fetch the total amount of nodes known by the source.
+    fn fetch_node_count(&self) -> impl Future<Output = Result<u64, NodeCounterError>> + Send;
+
+    /// Fetch all nodes known by the source.
+    fn fetch_nodes(&self) -> impl Future<Output = Result<Vec<Node>, NodeCounterError>> + Send;
+}
+
+/// The built in node sources.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// The graphql indexer.
+    #[default]
+    GraphQL,
+    /// The Grid Proxy REST API, which can be ahead of the indexer.
+    GridProxy,
+}
+
+impl Source {
+    /// All built in sources.
+    pub const ALL: [Source; 2] = [Source::GraphQL, Source::GridProxy];
+
+    /// Short lowercase name of the source.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Source::GraphQL => "graphql",
+            Source::GridProxy => "gridproxy",
+        }
+    }
+
+    /// The default endpoint of this source on a network.
+    pub fn url(&self, network: Network) -> &'static str {
+        match self {
+            Source::GraphQL => network.graphql_url(),
+            Source::GridProxy => network.grid_proxy_url(),
+        }
+    }
+}
+
+name_impls!(Source, "source");
+
+/// Decode the nodes from the recorded responses of a fetch from a source.
+pub(crate) fn decode_responses(
+    source: Source,
+    endpoint: &str,
+    responses: &[RawResponse],
+) -> Result<Vec<Node>, NodeCounterError> {
+    match source {
+        Source::GraphQL => graphql::decode_responses(endpoint, responses),
+        Source::GridProxy => grid_proxy::decode_responses(endpoint, responses),
+    }
+}
+
+/// Details of a farm, which are attached to the nodes of the farm.
+pub(crate) struct Farm {
+    pub farm_id: u32,
+    pub certification: Option<FarmCertification>,
+    pub dedicated: Option<bool>,
+}
+
+/// Attach the details of their farm to the nodes. Nodes of unknown farms are left as is.
+pub(crate) fn with_farms(nodes: Vec<Node>, farms: Vec<Farm>) -> Vec<Node> {
+    let farms = farms
+        .into_iter()
+        .map(|farm| (farm.farm_id, farm))
+        .collect::<HashMap<_, _>>();
+    nodes
+        .into_iter()
+        .map(|node| match farms.get(&node.farm_id()) {
+            Some(farm) => node.with_farm(farm.certification, farm.dedicated),
+            None => node,
+        })
+        .collect()
+}
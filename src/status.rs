@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// Whether a node is online.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeStatus {
+    /// The node is online and reporting.
+    Up,
+    /// The node is powered off to save energy, and is woken up when needed.
+    Standby,
+    /// The node stopped reporting.
+    Down,
+}
+
+impl NodeStatus {
+    /// All statuses.
+    pub const ALL: [NodeStatus; 3] = [NodeStatus::Up, NodeStatus::Standby, NodeStatus::Down];
+
+    /// Short lowercase name of the status.
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeStatus::Up => "up",
+            NodeStatus::Standby => "standby",
+            NodeStatus::Down => "down",
+        }
+    }
+}
+
+name_impls!(NodeStatus, "node status");
@@ -0,0 +1,404 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, types::Type, Connection, Params, Row};
+
+use crate::{Location, Network, Node, NodeCounterError, Resources};
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS snapshots (
+    id INTEGER PRIMARY KEY,
+    taken_at INTEGER NOT NULL,
+    network TEXT NOT NULL,
+    endpoint TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS snapshots_network_taken_at ON snapshots (network, taken_at);
+CREATE TABLE IF NOT EXISTS snapshot_nodes (
+    snapshot_id INTEGER NOT NULL REFERENCES snapshots (id) ON DELETE CASCADE,
+    node_id INTEGER NOT NULL,
+    farm_id INTEGER NOT NULL,
+    created INTEGER NOT NULL,
+    cru INTEGER NOT NULL,
+    mru INTEGER NOT NULL,
+    sru INTEGER NOT NULL,
+    hru INTEGER NOT NULL,
+    PRIMARY KEY (snapshot_id, node_id)
+);
+"#;
+
+/// Columns added to `snapshot_nodes` after its creation, added to existing stores when opened.
+const NODE_COLUMNS: &[(&str, &str)] = &[
+    ("country", "TEXT"),
+    ("city", "TEXT"),
+    ("latitude", "REAL"),
+    ("longitude", "REAL"),
+    ("certification", "TEXT"),
+    ("farm_certification", "TEXT"),
+    ("dedicated_farm", "INTEGER"),
+    ("used_cru", "INTEGER"),
+    ("used_mru", "INTEGER"),
+    ("used_sru", "INTEGER"),
+    ("used_hru", "INTEGER"),
+    ("updated_at", "INTEGER"),
+    ("status", "TEXT"),
+];
+
+/// A stored fetch of all nodes of a network.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    id: i64,
+    taken_at: DateTime<Utc>,
+    network: Network,
+    endpoint: String,
+    node_count: u64,
+}
+
+impl Snapshot {
+    /// Id of the snapshot in the store.
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Time at which the nodes were fetched.
+    pub fn taken_at(&self) -> DateTime<Utc> {
+        self.taken_at
+    }
+
+    /// The network the nodes were fetched from.
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Endpoint the nodes were fetched from.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Amount of nodes in the snapshot.
+    pub fn node_count(&self) -> u64 {
+        self.node_count
+    }
+}
+
+/// A SQLite database holding snapshots of fetched nodes.
+pub struct SnapshotStore {
+    path: PathBuf,
+    conn: Connection,
+}
+
+impl SnapshotStore {
+    /// Open the store at the given path, creating it if it does not exist yet.
+    pub fn open(path: &Path) -> Result<Self, NodeCounterError> {
+        let conn = Connection::open(path)
+            .and_then(|conn| {
+                conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+                conn.execute_batch(SCHEMA)?;
+                migrate(&conn)?;
+                Ok(conn)
+            })
+            .map_err(|source| NodeCounterError::Store {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            conn,
+        })
+    }
+
+    /// Store a snapshot of the given nodes, returning the snapshot.
+    pub fn save(
+        &mut self,
+        network: Network,
+        endpoint: &str,
+        taken_at: DateTime<Utc>,
+        nodes: &[Node],
+    ) -> Result<Snapshot, NodeCounterError> {
+        let id = insert_snapshot(&mut self.conn, network, endpoint, taken_at, nodes)
+            .map_err(|source| self.error(source))?;
+
+        Ok(Snapshot {
+            id,
+            taken_at,
+            network,
+            endpoint: endpoint.to_string(),
+            node_count: nodes.len() as u64,
+        })
+    }
+
+    /// All snapshots in the store, oldest first.
+    pub fn snapshots(&self) -> Result<Vec<Snapshot>, NodeCounterError> {
+        query_snapshots(&self.conn, "", params![]).map_err(|source| self.error(source))
+    }
+
+    /// Get a snapshot by id.
+    pub fn snapshot(&self, id: i64) -> Result<Snapshot, NodeCounterError> {
+        query_snapshots(&self.conn, "WHERE s.id = ?1", params![id])
+            .map_err(|source| self.error(source))?
+            .pop()
+            .ok_or_else(|| NodeCounterError::SnapshotNotFound {
+                path: self.path.clone(),
+                snapshot: id.to_string(),
+            })
+    }
+
+    /// Get the most recent snapshot of a network taken at or before `at`.
+    pub fn snapshot_as_of(
+        &self,
+        network: Network,
+        at: DateTime<Utc>,
+    ) -> Result<Snapshot, NodeCounterError> {
+        query_snapshots(
+            &self.conn,
+            "WHERE s.network = ?1 AND s.taken_at <= ?2",
+            params![network.name(), at.timestamp()],
+        )
+        .map_err(|source| self.error(source))?
+        .pop()
+        .ok_or_else(|| NodeCounterError::SnapshotNotFound {
+            path: self.path.clone(),
+            snapshot: format!("of {network} as of {at}"),
+        })
+    }
+
+    /// Load all nodes of a snapshot.
+    pub fn load(&self, snapshot: &Snapshot) -> Result<Vec<Node>, NodeCounterError> {
+        query_nodes(&self.conn, snapshot.id).map_err(|source| self.error(source))
+    }
+
+    fn error(&self, source: rusqlite::Error) -> NodeCounterError {
+        NodeCounterError::Store {
+            path: self.path.clone(),
+            source,
+        }
+    }
+}
+
+/// Add all missing columns of [`NODE_COLUMNS`] to `snapshot_nodes`.
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let existing = conn
+        .prepare("SELECT name FROM pragma_table_info('snapshot_nodes')")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (name, ty) in NODE_COLUMNS {
+        if !existing.iter().any(|column| column == name) {
+            conn.execute_batch(&format!(
+                "ALTER TABLE snapshot_nodes ADD COLUMN {name} {ty}"
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+fn insert_snapshot(
+    conn: &mut Connection,
+    network: Network,
+    endpoint: &str,
+    taken_at: DateTime<Utc>,
+    nodes: &[Node],
+) -> rusqlite::Result<i64> {
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO snapshots (taken_at, network, endpoint) VALUES (?1, ?2, ?3)",
+        params![taken_at.timestamp(), network.name(), endpoint],
+    )?;
+    let id = tx.last_insert_rowid();
+
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO snapshot_nodes (snapshot_id, node_id, farm_id, created, cru, mru, sru, hru,
+                country, city, latitude, longitude, certification, farm_certification, dedicated_farm,
+                used_cru, used_mru, used_sru, used_hru, updated_at, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                ?18, ?19, ?20, ?21)",
+        )?;
+        for node in nodes {
+            let resources = node.resources_total();
+            let used = node.resources_used();
+            // SQLite integers are signed, resource amounts comfortably fit.
+            stmt.execute(params![
+                id,
+                node.node_id(),
+                node.farm_id(),
+                node.created(),
+                resources.cru() as i64,
+                resources.mru() as i64,
+                resources.sru() as i64,
+                resources.hru() as i64,
+                node.country(),
+                node.city(),
+                node.location().map(|l| l.latitude()),
+                node.location().map(|l| l.longitude()),
+                node.certification().map(|c| c.name()),
+                node.farm_certification().map(|c| c.name()),
+                node.dedicated_farm(),
+                used.map(|r| r.cru() as i64),
+                used.map(|r| r.mru() as i64),
+                used.map(|r| r.sru() as i64),
+                used.map(|r| r.hru() as i64),
+                node.updated_at(),
+                node.status().map(|s| s.name()),
+            ])?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(id)
+}
+
+fn query_snapshots(
+    conn: &Connection,
+    filter: &str,
+    params: impl Params,
+) -> rusqlite::Result<Vec<Snapshot>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT s.id, s.taken_at, s.network, s.endpoint,
+            (SELECT COUNT(*) FROM snapshot_nodes n WHERE n.snapshot_id = s.id)
+         FROM snapshots s {filter} ORDER BY s.taken_at, s.id"
+    ))?;
+    let snapshots = stmt
+        .query_map(params, |row| {
+            Ok(Snapshot {
+                id: row.get(0)?,
+                taken_at: DateTime::from_timestamp(row.get(1)?, 0).unwrap_or_default(),
+                network: row.get::<_, String>(2)?.parse().map_err(|e: String| {
+                    rusqlite::Error::FromSqlConversionFailure(2, Type::Text, e.into())
+                })?,
+                endpoint: row.get(3)?,
+                node_count: row.get::<_, i64>(4)? as u64,
+            })
+        })?
+        .collect();
+    snapshots
+}
+
+fn query_nodes(conn: &Connection, snapshot_id: i64) -> rusqlite::Result<Vec<Node>> {
+    let mut stmt = conn.prepare(
+        "SELECT node_id, farm_id, created, cru, mru, sru, hru, country, city, latitude, longitude,
+            certification, farm_certification, dedicated_farm, used_cru, used_mru, used_sru, used_hru,
+            updated_at, status
+         FROM snapshot_nodes WHERE snapshot_id = ?1 ORDER BY node_id",
+    )?;
+    let nodes = stmt
+        .query_map(params![snapshot_id], |row| {
+            Ok(Node::new(
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                Resources::new(
+                    row.get::<_, i64>(3)? as u64,
+                    row.get::<_, i64>(4)? as u64,
+                    row.get::<_, i64>(5)? as u64,
+                    row.get::<_, i64>(6)? as u64,
+                ),
+            )
+            .with_location(
+                row.get(7)?,
+                row.get(8)?,
+                match (row.get(9)?, row.get(10)?) {
+                    (Some(latitude), Some(longitude)) => Location::new(latitude, longitude),
+                    _ => None,
+                },
+            )
+            .with_certification(parse(row.get(11)?))
+            .with_farm(parse(row.get(12)?), row.get(13)?)
+            .with_resources_used(optional_resources(row, 14)?)
+            .with_activity(row.get(18)?, parse(row.get(19)?)))
+        })?
+        .collect();
+    nodes
+}
+
+/// Read resources stored in 4 nullable columns, starting at `idx`. `None` if any is null.
+fn optional_resources(row: &Row, idx: usize) -> rusqlite::Result<Option<Resources>> {
+    let mut values = [0; 4];
+    for (offset, value) in values.iter_mut().enumerate() {
+        match row.get::<_, Option<i64>>(idx + offset)? {
+            Some(v) => *value = v as u64,
+            None => return Ok(None),
+        }
+    }
+    let [cru, mru, sru, hru] = values;
+    Ok(Some(Resources::new(cru, mru, sru, hru)))
+}
+
+/// Parse an optional stored name, ignoring unknown values.
+fn parse<T: FromStr>(name: Option<String>) -> Option<T> {
+    name.and_then(|name| name.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FarmCertification, NodeCertification, NodeStatus};
+
+    fn open() -> SnapshotStore {
+        SnapshotStore::open(Path::new(":memory:")).unwrap()
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(secs, 0).unwrap()
+    }
+
+    #[test]
+    fn saved_nodes_load_unchanged() {
+        let nodes = [
+            Node::new(1, 10, 100, Resources::new(8, 16, 32, 64))
+                .with_location(
+                    Some("Belgium".to_string()),
+                    Some("Ghent".to_string()),
+                    Location::new(51.05, 3.72),
+                )
+                .with_certification(Some(NodeCertification::Certified))
+                .with_farm(Some(FarmCertification::Gold), Some(true))
+                .with_resources_used(Some(Resources::new(1, 2, 3, 4)))
+                .with_activity(Some(200), Some(NodeStatus::Standby)),
+            Node::new(2, 20, 150, Resources::new(4, 0, 0, 0)),
+        ];
+        let mut store = open();
+
+        let saved = store
+            .save(Network::Testnet, "https://example.com", at(300), &nodes)
+            .unwrap();
+        let snapshot = store.snapshot(saved.id()).unwrap();
+        assert_eq!(snapshot.network(), Network::Testnet);
+        assert_eq!(snapshot.endpoint(), "https://example.com");
+        assert_eq!(snapshot.taken_at(), at(300));
+        assert_eq!(snapshot.node_count(), 2);
+
+        let loaded = store.load(&snapshot).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&nodes).unwrap()
+        );
+    }
+
+    #[test]
+    fn snapshot_as_of_picks_the_latest_of_the_network() {
+        let mut store = open();
+        let first = store.save(Network::Mainnet, "", at(100), &[]).unwrap();
+        let second = store.save(Network::Mainnet, "", at(200), &[]).unwrap();
+        store.save(Network::Testnet, "", at(150), &[]).unwrap();
+
+        let as_of = |secs| {
+            store
+                .snapshot_as_of(Network::Mainnet, at(secs))
+                .unwrap()
+                .id()
+        };
+        assert_eq!(as_of(150), first.id());
+        assert_eq!(as_of(200), second.id());
+        assert!(matches!(
+            store.snapshot_as_of(Network::Mainnet, at(50)),
+            Err(NodeCounterError::SnapshotNotFound { .. })
+        ));
+        assert!(matches!(
+            store.snapshot(42),
+            Err(NodeCounterError::SnapshotNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,665 @@
+use std::{fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::{Country, FarmCertification, NodeCertification, NodeCounterError, NodeStatus};
+
+#[derive(Serialize)]
+pub struct GraphQLRequest<'a, T: Serialize> {
+    pub operation_name: &'a str,
+    pub query: &'a str,
+    pub variables: Option<T>,
+}
+
+/// Variables for a paginated query.
+#[derive(Serialize)]
+pub struct PageVariables {
+    pub limit: u32,
+    pub offset: u32,
+}
+
+/// The full response envelope of a graphql query.
+#[derive(Deserialize)]
+pub struct GraphQLResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQLError>,
+    extensions: Option<Value>,
+}
+
+impl<T> GraphQLResponse<T> {
+    /// The data returned by the server, if any.
+    pub fn data(&self) -> Option<&T> {
+        self.data.as_ref()
+    }
+
+    /// Errors returned by the server.
+    pub fn errors(&self) -> &[GraphQLError] {
+        &self.errors
+    }
+
+    /// Extensions returned by the server, if any.
+    pub fn extensions(&self) -> Option<&Value> {
+        self.extensions.as_ref()
+    }
+
+    /// Consume the response, returning the data if the server did not report any errors.
+    ///
+    /// If the server returned errors alongside (partial) data, the response is still considered
+    /// failed.
+    pub fn into_result(self) -> Result<T, NodeCounterError> {
+        match (self.data, self.errors.is_empty()) {
+            (Some(data), true) => Ok(data),
+            (None, true) => Err(NodeCounterError::MissingData),
+            (data, false) => Err(NodeCounterError::GraphQL {
+                errors: self.errors,
+                partial: data.is_some(),
+            }),
+        }
+    }
+}
+
+/// An error reported by a graphql server.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GraphQLError {
+    message: String,
+    #[serde(default)]
+    locations: Vec<GraphQLErrorLocation>,
+    #[serde(default)]
+    path: Vec<Value>,
+    extensions: Option<Value>,
+}
+
+impl GraphQLError {
+    /// The error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Locations in the query the error relates to.
+    pub fn locations(&self) -> &[GraphQLErrorLocation] {
+        &self.locations
+    }
+
+    /// Path to the response field which caused the error. Segments are either field names or
+    /// list indices.
+    pub fn path(&self) -> &[Value] {
+        &self.path
+    }
+
+    /// Extensions attached to the error, if any.
+    pub fn extensions(&self) -> Option<&Value> {
+        self.extensions.as_ref()
+    }
+}
+
+impl fmt::Display for GraphQLError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if !self.path.is_empty() {
+            let path = self
+                .path
+                .iter()
+                .map(|segment| match segment {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(".");
+            write!(f, " (at {path})")?;
+        }
+        for location in &self.locations {
+            write!(f, " [line {}, column {}]", location.line, location.column)?;
+        }
+        Ok(())
+    }
+}
+
+/// A location in a graphql query.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct GraphQLErrorLocation {
+    line: u32,
+    column: u32,
+}
+
+impl GraphQLErrorLocation {
+    /// Line in the query, starting from 1.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Column in the query, starting from 1.
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NodeReply {
+    nodes: Vec<Node>,
+}
+
+impl NodeReply {
+    /// The nodes in the reply.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Consume the reply, returning the nodes.
+    pub fn into_nodes(self) -> Vec<Node> {
+        self.nodes
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NodeCountReply {
+    #[serde(rename = "nodesConnection")]
+    nodes_connection: NodesConnection,
+}
+
+#[derive(Deserialize)]
+struct NodesConnection {
+    #[serde(rename = "totalCount")]
+    total_count: u64,
+}
+
+impl NodeCountReply {
+    /// The total amount of nodes known by the server.
+    pub fn total_count(&self) -> u64 {
+        self.nodes_connection.total_count
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Node {
+    #[serde(rename = "nodeID")]
+    node_id: u32,
+    #[serde(rename = "farmID")]
+    farm_id: u32,
+    created: i64,
+    #[serde(rename = "resourcesTotal")]
+    resources_total: Resources,
+    #[serde(
+        rename = "resourcesUsed",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    resources_used: Option<Resources>,
+    #[serde(
+        default,
+        deserialize_with = "de_name",
+        skip_serializing_if = "Option::is_none"
+    )]
+    country: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "de_name",
+        skip_serializing_if = "Option::is_none"
+    )]
+    city: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "de_location",
+        skip_serializing_if = "Option::is_none"
+    )]
+    location: Option<Location>,
+    #[serde(
+        default,
+        deserialize_with = "de_known",
+        skip_serializing_if = "Option::is_none"
+    )]
+    certification: Option<NodeCertification>,
+    #[serde(
+        rename = "farmCertification",
+        default,
+        deserialize_with = "de_known",
+        skip_serializing_if = "Option::is_none"
+    )]
+    farm_certification: Option<FarmCertification>,
+    #[serde(
+        rename = "dedicatedFarm",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    dedicated_farm: Option<bool>,
+    #[serde(
+        rename = "updatedAt",
+        default,
+        deserialize_with = "de_timestamp",
+        skip_serializing_if = "Option::is_none"
+    )]
+    updated_at: Option<i64>,
+    #[serde(
+        default,
+        deserialize_with = "de_known",
+        skip_serializing_if = "Option::is_none"
+    )]
+    status: Option<NodeStatus>,
+}
+
+impl Node {
+    /// Create a new `Node`.
+    pub fn new(node_id: u32, farm_id: u32, created: i64, resources_total: Resources) -> Self {
+        Self {
+            node_id,
+            farm_id,
+            created,
+            resources_total,
+            resources_used: None,
+            country: None,
+            city: None,
+            location: None,
+            certification: None,
+            farm_certification: None,
+            dedicated_farm: None,
+            updated_at: None,
+            status: None,
+        }
+    }
+
+    /// Set where the node is located. Empty names are treated as unknown.
+    pub fn with_location(
+        mut self,
+        country: Option<String>,
+        city: Option<String>,
+        location: Option<Location>,
+    ) -> Self {
+        self.country = country.filter(|country| !country.trim().is_empty());
+        self.city = city.filter(|city| !city.trim().is_empty());
+        self.location = location;
+        self
+    }
+
+    /// Set the resources of the node which are in use.
+    pub fn with_resources_used(mut self, resources_used: Option<Resources>) -> Self {
+        self.resources_used = resources_used;
+        self
+    }
+
+    /// Set how the node is certified.
+    pub fn with_certification(mut self, certification: Option<NodeCertification>) -> Self {
+        self.certification = certification;
+        self
+    }
+
+    /// Set the certification of the farm of the node, and whether it is a dedicated farm.
+    pub fn with_farm(
+        mut self,
+        certification: Option<FarmCertification>,
+        dedicated: Option<bool>,
+    ) -> Self {
+        self.farm_certification = certification;
+        self.dedicated_farm = dedicated;
+        self
+    }
+
+    /// Set when the node last reported to the grid, as unix timestamp, and its status as
+    /// reported by the source.
+    pub fn with_activity(mut self, updated_at: Option<i64>, status: Option<NodeStatus>) -> Self {
+        self.updated_at = updated_at;
+        self.status = status;
+        self
+    }
+
+    /// The id of the node on the grid.
+    pub fn node_id(&self) -> u32 {
+        self.node_id
+    }
+
+    /// The id of the farm the node belongs to.
+    pub fn farm_id(&self) -> u32 {
+        self.farm_id
+    }
+
+    /// Unix timestamp (in seconds) at which the node was created.
+    pub fn created(&self) -> i64 {
+        self.created
+    }
+
+    /// Total resources of the node.
+    pub fn resources_total(&self) -> &Resources {
+        &self.resources_total
+    }
+
+    /// Resources of the node in use at the time it was fetched, if reported by the source.
+    pub fn resources_used(&self) -> Option<&Resources> {
+        self.resources_used.as_ref()
+    }
+
+    /// The country of the node, as reported by the source.
+    pub fn country(&self) -> Option<&str> {
+        self.country.as_deref()
+    }
+
+    /// The country of the node, normalised to an ISO 3166-1 country. `None` if the country is
+    /// unknown or not recognised.
+    pub fn iso_country(&self) -> Option<&'static Country> {
+        self.country.as_deref().and_then(Country::find)
+    }
+
+    /// The city of the node, as reported by the source.
+    pub fn city(&self) -> Option<&str> {
+        self.city.as_deref()
+    }
+
+    /// The coordinates of the node.
+    pub fn location(&self) -> Option<Location> {
+        self.location
+    }
+
+    /// How the node is certified.
+    pub fn certification(&self) -> Option<NodeCertification> {
+        self.certification
+    }
+
+    /// The certification of the farm of the node.
+    pub fn farm_certification(&self) -> Option<FarmCertification> {
+        self.farm_certification
+    }
+
+    /// Whether the farm of the node is dedicated.
+    pub fn dedicated_farm(&self) -> Option<bool> {
+        self.dedicated_farm
+    }
+
+    /// Unix timestamp (in seconds) at which the node last reported to the grid.
+    pub fn updated_at(&self) -> Option<i64> {
+        self.updated_at
+    }
+
+    /// The status of the node as reported by the source at the time it was fetched.
+    pub fn status(&self) -> Option<NodeStatus> {
+        self.status
+    }
+
+    /// The status of the node at unix timestamp `at`, if the node was created before it. `None`
+    /// if the activity of the node is unknown.
+    ///
+    /// A node which reported at or after `at` was up. A node which last reported in the
+    /// `staleness` seconds before `at` has the status reported by the source, or is up if the
+    /// source has none. Nodes which didn't report for longer are down. Without a last report,
+    /// the status reported by the source is used for any time.
+    ///
+    /// Only the last report is known, so a node is up at any time between its creation and its
+    /// last report, even if it was down in between.
+    pub fn status_at(&self, at: i64, staleness: i64) -> Option<NodeStatus> {
+        if self.created >= at {
+            return None;
+        }
+        match self.updated_at {
+            Some(updated_at) if at <= updated_at => Some(NodeStatus::Up),
+            Some(updated_at) if at <= updated_at.saturating_add(staleness) => {
+                Some(self.status.unwrap_or(NodeStatus::Up))
+            }
+            Some(_) => Some(NodeStatus::Down),
+            None => self.status,
+        }
+    }
+}
+
+/// Coordinates of a node, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Location {
+    latitude: f64,
+    longitude: f64,
+}
+
+impl Location {
+    /// Create a new `Location`. Returns `None` if the coordinates are out of range.
+    pub fn new(latitude: f64, longitude: f64) -> Option<Self> {
+        ((-90.0..=90.).contains(&latitude) && (-180.0..=180.).contains(&longitude)).then_some(
+            Self {
+                latitude,
+                longitude,
+            },
+        )
+    }
+
+    /// Latitude, positive north of the equator.
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    /// Longitude, positive east of Greenwich.
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    /// Great-circle distance to another location in km, using the haversine formula.
+    pub fn distance_km(&self, other: &Location) -> f64 {
+        const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.longitude - self.longitude).to_radians();
+        let a = (dlat / 2.).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.).sin().powi(2);
+        2. * EARTH_RADIUS_KM * a.sqrt().min(1.).asin()
+    }
+}
+
+impl FromStr for Location {
+    type Err = String;
+
+    /// Parse `<latitude>,<longitude>` in degrees.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid location \"{s}\", expected <latitude>,<longitude>");
+        let (latitude, longitude) = s.split_once(',').ok_or_else(invalid)?;
+        let latitude = latitude.trim().parse().map_err(|_| invalid())?;
+        let longitude = longitude.trim().parse().map_err(|_| invalid())?;
+        Location::new(latitude, longitude).ok_or_else(invalid)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resources {
+    #[serde(deserialize_with = "de_u64")]
+    cru: u64,
+    #[serde(deserialize_with = "de_u64")]
+    mru: u64,
+    #[serde(deserialize_with = "de_u64")]
+    sru: u64,
+    #[serde(deserialize_with = "de_u64")]
+    hru: u64,
+}
+
+impl Resources {
+    /// Create a new `Resources`.
+    pub fn new(cru: u64, mru: u64, sru: u64, hru: u64) -> Self {
+        Self { cru, mru, sru, hru }
+    }
+
+    /// Amount of compute units (logical cores).
+    pub fn cru(&self) -> u64 {
+        self.cru
+    }
+
+    /// Amount of memory, in bytes.
+    pub fn mru(&self) -> u64 {
+        self.mru
+    }
+
+    /// Amount of SSD storage, in bytes.
+    pub fn sru(&self) -> u64 {
+        self.sru
+    }
+
+    /// Amount of HDD storage, in bytes.
+    pub fn hru(&self) -> u64 {
+        self.hru
+    }
+
+    /// Subtract `rhs` per resource, stopping at 0.
+    pub fn saturating_sub(&self, rhs: &Resources) -> Resources {
+        Resources {
+            cru: self.cru.saturating_sub(rhs.cru),
+            mru: self.mru.saturating_sub(rhs.mru),
+            sru: self.sru.saturating_sub(rhs.sru),
+            hru: self.hru.saturating_sub(rhs.hru),
+        }
+    }
+}
+
+impl std::ops::AddAssign<&Resources> for Resources {
+    fn add_assign(&mut self, rhs: &Resources) {
+        self.cru += rhs.cru;
+        self.mru += rhs.mru;
+        self.sru += rhs.sru;
+        self.hru += rhs.hru;
+    }
+}
+
+/// Subtract per resource, stopping at 0, see [`Resources::saturating_sub`].
+impl std::ops::Sub for Resources {
+    type Output = Resources;
+
+    fn sub(self, rhs: Resources) -> Resources {
+        self.saturating_sub(&rhs)
+    }
+}
+
+/// Deserialize an optional location with coordinates as number or string. Sources report missing
+/// locations as empty strings or zeroes, so missing or invalid coordinates result in `None`
+/// instead of an error.
+pub(crate) fn de_location<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Location>, D::Error> {
+    #[derive(Deserialize)]
+    struct RawLocation {
+        latitude: Option<Value>,
+        longitude: Option<Value>,
+    }
+
+    let coordinate = |value: Option<Value>| match value? {
+        Value::String(s) => s.trim().parse::<f64>().ok(),
+        Value::Number(num) => num.as_f64(),
+        _ => None,
+    };
+    let Some(raw) = Option::<RawLocation>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    Ok(
+        match (coordinate(raw.latitude), coordinate(raw.longitude)) {
+            (Some(latitude), Some(longitude)) if (latitude, longitude) != (0., 0.) => {
+                Location::new(latitude, longitude)
+            }
+            _ => None,
+        },
+    )
+}
+
+/// Deserialize an optional unix timestamp, given as number, numeric string or RFC 3339 date.
+/// Invalid values result in `None`.
+pub(crate) fn de_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<i64>, D::Error> {
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Number(num)) => num.as_i64(),
+        Some(Value::String(s)) => s.trim().parse().ok().or_else(|| {
+            chrono::DateTime::parse_from_rfc3339(s.trim())
+                .ok()
+                .map(|date| date.timestamp())
+        }),
+        _ => None,
+    })
+}
+
+/// Deserialize an optional name, treating empty names as unknown like [`Node::with_location`].
+pub(crate) fn de_name<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.filter(|name| !name.trim().is_empty()))
+}
+
+/// Deserialize an optional value from its name. Sources may add values, like new certification
+/// levels, so unknown names result in `None` instead of an error.
+pub(crate) fn de_known<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.and_then(|s| s.parse().ok()))
+}
+
+/// Helper function to deserialize an u64 which is returned as string (BigNum) in graphql.
+pub fn de_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) => s
+            .parse()
+            .map_err(|e| de::Error::custom(format!("invalid u64 string \"{s}\": {e}")))?,
+        Value::Number(num) => num
+            .as_u64()
+            .ok_or_else(|| de::Error::custom(format!("number {num} is not a valid u64")))?,
+        other => {
+            return Err(de::Error::custom(format!(
+                "expected a u64 as number or string, got {other}"
+            )))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_response(json: &str) -> Result<u64, NodeCounterError> {
+        serde_json::from_str::<GraphQLResponse<NodeCountReply>>(json)
+            .unwrap()
+            .into_result()
+            .map(|reply| reply.total_count())
+    }
+
+    #[test]
+    fn resources_subtract_down_to_zero() {
+        let less = Resources::new(1, 2, 3, 4);
+        let more = Resources::new(2, 1, 3, 5);
+        assert_eq!(more - less, Resources::new(1, 0, 0, 1));
+        assert_eq!(less - more, Resources::new(0, 1, 0, 0));
+    }
+
+    #[test]
+    fn into_result_returns_data_without_errors() {
+        let json = r#"{"data": {"nodesConnection": {"totalCount": 3}}, "extensions": {"a": 1}}"#;
+        assert_eq!(count_response(json).unwrap(), 3);
+    }
+
+    #[test]
+    fn into_result_maps_errors() {
+        let errors = r#""errors": [{"message": "boom", "path": ["nodes", 0], "locations": [{"line": 1, "column": 2}]}]"#;
+
+        match count_response(&format!(r#"{{"data": null, {errors}}}"#)) {
+            Err(NodeCounterError::GraphQL { errors, partial }) => {
+                assert!(!partial);
+                assert_eq!(
+                    errors[0].to_string(),
+                    "boom (at nodes.0) [line 1, column 2]"
+                );
+            }
+            other => panic!("expected graphql errors, got {other:?}"),
+        }
+        let partial =
+            format!(r#"{{"data": {{"nodesConnection": {{"totalCount": 3}}}}, {errors}}}"#);
+        assert!(matches!(
+            count_response(&partial),
+            Err(NodeCounterError::GraphQL { partial: true, .. })
+        ));
+        assert!(matches!(
+            count_response("{}"),
+            Err(NodeCounterError::MissingData)
+        ));
+    }
+
+    #[test]
+    fn empty_locations_deserialize_as_unknown() {
+        let json = r#"{"nodeID": 1, "farmID": 2, "created": 0,
+            "resourcesTotal": {"cru": "1", "mru": "0", "sru": "0", "hru": "0"},
+            "country": "", "city": " "}"#;
+        let node = serde_json::from_str::<Node>(json).unwrap();
+        assert_eq!(node.country(), None);
+        assert_eq!(node.city(), None);
+
+        let json = json.replace(r#""country": """#, r#""country": "Belgium""#);
+        let node = serde_json::from_str::<Node>(&json).unwrap();
+        assert_eq!(node.country(), Some("Belgium"));
+    }
+}
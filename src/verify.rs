@@ -0,0 +1,345 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use serde::Serialize;
+
+use crate::{diff::ResourceDelta, Node, Resources};
+
+/// The kind of disagreement between the graphql indexer and the Grid Proxy about a node.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    /// The node is only known by the graphql indexer.
+    #[default]
+    OnlyGraphql,
+    /// The node is only known by the Grid Proxy.
+    OnlyGridProxy,
+    /// The sources report the node in a different farm.
+    FarmMismatch,
+    /// The sources report different total resources for the node.
+    ResourcesMismatch,
+}
+
+/// A single disagreement about a node. A node with both a different farm and different
+/// resources has 2 discrepancies.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Discrepancy {
+    discrepancy: DiscrepancyKind,
+    #[serde(rename = "node id")]
+    node_id: u32,
+    #[serde(rename = "graphql farm id")]
+    graphql_farm_id: Option<u32>,
+    #[serde(rename = "grid proxy farm id")]
+    grid_proxy_farm_id: Option<u32>,
+    #[serde(flatten)]
+    delta: ResourceDelta,
+}
+
+impl Discrepancy {
+    /// The kind of discrepancy.
+    pub fn kind(&self) -> DiscrepancyKind {
+        self.discrepancy
+    }
+
+    /// Id of the node.
+    pub fn node_id(&self) -> u32 {
+        self.node_id
+    }
+
+    /// Farm of the node according to the graphql indexer, if it knows the node.
+    pub fn graphql_farm_id(&self) -> Option<u32> {
+        self.graphql_farm_id
+    }
+
+    /// Farm of the node according to the Grid Proxy, if it knows the node.
+    pub fn grid_proxy_farm_id(&self) -> Option<u32> {
+        self.grid_proxy_farm_id
+    }
+
+    /// Grid Proxy resources minus graphql resources. Zero for farm mismatches.
+    pub fn delta(&self) -> &ResourceDelta {
+        &self.delta
+    }
+}
+
+/// Totals of a verification.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct VerifySummary {
+    #[serde(rename = "graphql nodes")]
+    graphql_nodes: u64,
+    #[serde(rename = "grid proxy nodes")]
+    grid_proxy_nodes: u64,
+    #[serde(rename = "node delta")]
+    node_delta: i64,
+    #[serde(rename = "only in graphql")]
+    only_graphql: u64,
+    #[serde(rename = "only in grid proxy")]
+    only_grid_proxy: u64,
+    #[serde(rename = "farm mismatches")]
+    farm_mismatches: u64,
+    #[serde(rename = "resource mismatches")]
+    resource_mismatches: u64,
+    #[serde(rename = "mismatched nodes")]
+    mismatched_nodes: u64,
+    #[serde(flatten)]
+    delta: ResourceDelta,
+}
+
+impl VerifySummary {
+    /// Grid Proxy node count minus graphql node count.
+    pub fn node_delta(&self) -> i64 {
+        self.node_delta
+    }
+
+    /// Amount of distinct nodes with at least 1 discrepancy.
+    pub fn mismatched_nodes(&self) -> u64 {
+        self.mismatched_nodes
+    }
+
+    /// Grid Proxy total resources minus graphql total resources.
+    pub fn delta(&self) -> &ResourceDelta {
+        &self.delta
+    }
+}
+
+/// The result of cross-checking the nodes of the graphql indexer and the Grid Proxy.
+#[derive(Debug, Clone)]
+pub struct Verification {
+    graphql_nodes: u64,
+    grid_proxy_nodes: u64,
+    discrepancies: Vec<Discrepancy>,
+    delta: ResourceDelta,
+}
+
+impl Verification {
+    /// All discrepancies, ordered by node id.
+    pub fn discrepancies(&self) -> &[Discrepancy] {
+        &self.discrepancies
+    }
+
+    /// Amount of distinct nodes with at least 1 discrepancy.
+    pub fn mismatched_nodes(&self) -> u64 {
+        let mut ids = self
+            .discrepancies
+            .iter()
+            .map(Discrepancy::node_id)
+            .collect::<Vec<_>>();
+        ids.dedup();
+        ids.len() as u64
+    }
+
+    /// Totals of all discrepancies.
+    pub fn summary(&self) -> VerifySummary {
+        let count = |kind| {
+            self.discrepancies
+                .iter()
+                .filter(|d| d.discrepancy == kind)
+                .count() as u64
+        };
+        VerifySummary {
+            graphql_nodes: self.graphql_nodes,
+            grid_proxy_nodes: self.grid_proxy_nodes,
+            node_delta: self.grid_proxy_nodes as i64 - self.graphql_nodes as i64,
+            only_graphql: count(DiscrepancyKind::OnlyGraphql),
+            only_grid_proxy: count(DiscrepancyKind::OnlyGridProxy),
+            farm_mismatches: count(DiscrepancyKind::FarmMismatch),
+            resource_mismatches: count(DiscrepancyKind::ResourcesMismatch),
+            mismatched_nodes: self.mismatched_nodes(),
+            delta: self.delta,
+        }
+    }
+
+    /// Whether the amount of mismatched nodes is within `tolerance`.
+    pub fn within(&self, tolerance: Tolerance) -> bool {
+        let mismatched = self.mismatched_nodes();
+        match tolerance {
+            Tolerance::Nodes(max) => mismatched <= max,
+            Tolerance::Percent(max) => {
+                let total = self.graphql_nodes.max(self.grid_proxy_nodes);
+                total == 0 || mismatched as f64 / total as f64 * 100. <= max
+            }
+        }
+    }
+}
+
+/// Cross-check the nodes fetched from the graphql indexer against the nodes fetched from the
+/// Grid Proxy, by node id.
+pub fn verify(graphql: &[Node], grid_proxy: &[Node]) -> Verification {
+    let zero = Resources::default();
+    let graphql_by_id = graphql
+        .iter()
+        .map(|node| (node.node_id(), node))
+        .collect::<HashMap<_, _>>();
+    let grid_proxy_by_id = grid_proxy
+        .iter()
+        .map(|node| (node.node_id(), node))
+        .collect::<HashMap<_, _>>();
+
+    let mut discrepancies = Vec::new();
+    for node in graphql {
+        if !grid_proxy_by_id.contains_key(&node.node_id()) {
+            discrepancies.push(Discrepancy {
+                discrepancy: DiscrepancyKind::OnlyGraphql,
+                node_id: node.node_id(),
+                graphql_farm_id: Some(node.farm_id()),
+                grid_proxy_farm_id: None,
+                delta: ResourceDelta::between(node.resources_total(), &zero),
+            });
+        }
+    }
+    for node in grid_proxy {
+        let Some(graphql_node) = graphql_by_id.get(&node.node_id()) else {
+            discrepancies.push(Discrepancy {
+                discrepancy: DiscrepancyKind::OnlyGridProxy,
+                node_id: node.node_id(),
+                graphql_farm_id: None,
+                grid_proxy_farm_id: Some(node.farm_id()),
+                delta: ResourceDelta::between(&zero, node.resources_total()),
+            });
+            continue;
+        };
+
+        if graphql_node.farm_id() != node.farm_id() {
+            discrepancies.push(Discrepancy {
+                discrepancy: DiscrepancyKind::FarmMismatch,
+                node_id: node.node_id(),
+                graphql_farm_id: Some(graphql_node.farm_id()),
+                grid_proxy_farm_id: Some(node.farm_id()),
+                delta: ResourceDelta::default(),
+            });
+        }
+        if graphql_node.resources_total() != node.resources_total() {
+            discrepancies.push(Discrepancy {
+                discrepancy: DiscrepancyKind::ResourcesMismatch,
+                node_id: node.node_id(),
+                graphql_farm_id: Some(graphql_node.farm_id()),
+                grid_proxy_farm_id: Some(node.farm_id()),
+                delta: ResourceDelta::between(
+                    graphql_node.resources_total(),
+                    node.resources_total(),
+                ),
+            });
+        }
+    }
+    // The sort is stable, so a farm mismatch stays before a resource mismatch of the same node.
+    discrepancies.sort_by_key(|d| d.node_id);
+
+    let mut delta = ResourceDelta::default();
+    for d in &discrepancies {
+        delta += d.delta;
+    }
+
+    Verification {
+        graphql_nodes: graphql.len() as u64,
+        grid_proxy_nodes: grid_proxy.len() as u64,
+        discrepancies,
+        delta,
+    }
+}
+
+/// The maximum amount of mismatched nodes for a verification to pass, either as absolute amount
+/// or as percentage of the largest node set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tolerance {
+    /// An absolute amount of nodes.
+    Nodes(u64),
+    /// A percentage of the nodes.
+    Percent(f64),
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance::Nodes(0)
+    }
+}
+
+impl fmt::Display for Tolerance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tolerance::Nodes(nodes) => write!(f, "{nodes}"),
+            Tolerance::Percent(percent) => write!(f, "{percent}%"),
+        }
+    }
+}
+
+impl FromStr for Tolerance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid tolerance \"{s}\", expected an amount of nodes or a percentage like 0.5%"
+            )
+        };
+        match s.trim().strip_suffix('%') {
+            Some(percent) => match percent.trim().parse::<f64>() {
+                Ok(percent) if percent >= 0. => Ok(Tolerance::Percent(percent)),
+                _ => Err(invalid()),
+            },
+            None => s
+                .trim()
+                .parse()
+                .map(Tolerance::Nodes)
+                .map_err(|_| invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: u32, farm: u32, cru: u64) -> Node {
+        Node::new(id, farm, 0, Resources::new(cru, 0, 0, 0))
+    }
+
+    #[test]
+    fn tolerance_parses_node_counts_and_percentages() {
+        assert_eq!("3".parse(), Ok(Tolerance::Nodes(3)));
+        assert_eq!(" 3 ".parse(), Ok(Tolerance::Nodes(3)));
+        assert_eq!("0.5%".parse(), Ok(Tolerance::Percent(0.5)));
+        assert_eq!("2 %".parse(), Ok(Tolerance::Percent(2.)));
+        for invalid in ["", "%", "-1", "-1%", "1.5", "a%", "NaN%"] {
+            assert!(invalid.parse::<Tolerance>().is_err(), "{invalid:?} parsed");
+        }
+    }
+
+    #[test]
+    fn within_includes_the_boundary() {
+        // 1 mismatched node out of 4: node 4 moved farm.
+        let graphql = [node(1, 1, 1), node(2, 1, 1), node(3, 1, 1), node(4, 1, 1)];
+        let grid_proxy = [node(1, 1, 1), node(2, 1, 1), node(3, 1, 1), node(4, 2, 1)];
+        let verification = verify(&graphql, &grid_proxy);
+        assert_eq!(verification.mismatched_nodes(), 1);
+
+        assert!(verification.within(Tolerance::Nodes(1)));
+        assert!(!verification.within(Tolerance::Nodes(0)));
+        assert!(verification.within(Tolerance::Percent(25.)));
+        assert!(!verification.within(Tolerance::Percent(24.9)));
+
+        let empty = verify(&[], &[]);
+        assert!(empty.within(Tolerance::Nodes(0)));
+        assert!(empty.within(Tolerance::Percent(0.)));
+    }
+
+    #[test]
+    fn nodes_with_several_discrepancies_count_once() {
+        let graphql = [node(1, 1, 1), node(2, 1, 1)];
+        let grid_proxy = [node(1, 2, 2), node(2, 1, 1)];
+        let verification = verify(&graphql, &grid_proxy);
+
+        let kinds = verification
+            .discrepancies()
+            .iter()
+            .map(Discrepancy::kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                DiscrepancyKind::FarmMismatch,
+                DiscrepancyKind::ResourcesMismatch
+            ]
+        );
+        assert_eq!(verification.mismatched_nodes(), 1);
+        assert_eq!(verification.summary().mismatched_nodes(), 1);
+    }
+}